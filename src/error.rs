@@ -1,11 +1,73 @@
+use std::ops::Range;
+
 use thiserror::Error;
 
+use crate::Sample;
+
 #[derive(Error, Debug, PartialEq)]
 pub enum LlDoiceError {
     #[error("Probability must be between 0 and 1.0.")]
     InvalidProbability,
+    #[error("Probability {value} of outcome {outcome} is not between 0 and 1.")]
+    ProbabilityOutOfRange { outcome: Sample, value: f64 },
+    #[error("Probabilities sum to {total}, which is not within {tolerance} of 1.")]
+    InvalidTotal { total: f64, tolerance: f64 },
+    #[error("Probabilities sum to {total}, which does not fit in the probability type.")]
+    TotalOverflow { total: f64 },
+    #[error("Total probability is zero, so it cannot be normalized.")]
+    ZeroTotal,
+    #[error("Weight {value} of component {index} is not between 0 and 1.")]
+    InvalidWeight { index: usize, value: f64 },
+    #[error("Division by zero{}, which happens with probability {probability}.", at(.span))]
+    DivisionByZero {
+        probability: f64,
+        span: Option<Range<usize>>,
+    },
+    #[error("Outcome{} does not fit in a Sample.", at(.span))]
+    OutcomeOverflow { span: Option<Range<usize>> },
     #[error("Number of outcomes and probabilities must be equal.")]
     InvalidLength,
     #[error("Outcomes must always be in ascending order.")]
     UnorderedOutcomes,
+    #[error("Every outcome has to be rerolled, so rerolling never ends.")]
+    EndlessReroll,
+    #[error("Unexpected character '{character}' at {span:?}.")]
+    UnexpectedCharacter { character: char, span: Range<usize> },
+    #[error("Unexpected token at {span:?}.")]
+    UnexpectedToken { span: Range<usize> },
+    #[error("Unexpected end of expression at {span:?}.")]
+    UnexpectedEnd { span: Range<usize> },
+    #[error("Number at {span:?} is too large.")]
+    InvalidNumber { span: Range<usize> },
+    #[error("Invalid dice at {span:?}: {reason}.")]
+    InvalidDice {
+        span: Range<usize>,
+        reason: &'static str,
+    },
+}
+
+/// Where in a dice expression an error happened, if known.
+fn at(span: &Option<Range<usize>>) -> String {
+    span.as_ref()
+        .map(|span| format!(" at {span:?}"))
+        .unwrap_or_default()
+}
+
+impl LlDoiceError {
+    /// Attach the span of the expression that was being evaluated, unless the error already has one.
+    pub(crate) fn with_span(self, span: &Range<usize>) -> Self {
+        match self {
+            LlDoiceError::DivisionByZero {
+                probability,
+                span: None,
+            } => LlDoiceError::DivisionByZero {
+                probability,
+                span: Some(span.clone()),
+            },
+            LlDoiceError::OutcomeOverflow { span: None } => LlDoiceError::OutcomeOverflow {
+                span: Some(span.clone()),
+            },
+            other => other,
+        }
+    }
 }
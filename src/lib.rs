@@ -10,11 +10,18 @@
 
 mod error;
 pub mod numerics;
+pub mod parse;
 mod pdf;
+#[cfg(test)]
+mod test_utils;
 mod traits;
 
 pub use error::LlDoiceError;
-pub use pdf::PDF;
+pub use pdf::{
+    Convolve, CountPdf, DivisionPolicy, MinMaxPDF, Number, Opposed, Precision, Rounding, Sample,
+    TieRule, ZeroDivisor, PDF,
+};
+pub use traits::DiscStats;
 
 #[cfg(test)]
 mod tests {
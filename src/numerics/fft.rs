@@ -0,0 +1,111 @@
+use std::f64::consts::PI;
+
+use num::Complex;
+
+/// In-place iterative radix-2 Cooley-Tukey FFT.
+///
+/// The length of the buffer must be a power of two.
+/// When `inverse` is set, the inverse transform is computed (including the 1/n scaling).
+fn fft(buf: &mut [Complex<f64>], inverse: bool) {
+    let n = buf.len();
+    debug_assert!(n.is_power_of_two());
+
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+
+    // Butterflies
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let step = Complex::from_polar(1.0, sign * 2.0 * PI / len as f64);
+        for chunk in buf.chunks_exact_mut(len) {
+            let (lo, hi) = chunk.split_at_mut(len / 2);
+            let mut w = Complex::new(1.0, 0.0);
+            for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+                let t = *b * w;
+                *b = *a - t;
+                *a += t;
+                w *= step;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f64;
+        for v in buf.iter_mut() {
+            *v *= scale;
+        }
+    }
+}
+
+/// Computes the linear convolution of two real sequences using the FFT.
+///
+/// Both sequences are packed into a single complex buffer (lhs in the real part, rhs in the imaginary part),
+/// so only one forward and one inverse transform are needed.
+/// The absolute error of each entry is in the order of `f64::EPSILON * log2(n)` times the largest input magnitudes.
+pub(crate) fn convolve_real(lhs: &[f64], rhs: &[f64]) -> Vec<f64> {
+    if lhs.is_empty() || rhs.is_empty() {
+        return Vec::new();
+    }
+
+    let out_len = lhs.len() + rhs.len() - 1;
+    let n = out_len.next_power_of_two();
+
+    let mut buf = vec![Complex::new(0.0, 0.0); n];
+    for (i, v) in lhs.iter().enumerate() {
+        buf[i].re = *v;
+    }
+    for (i, v) in rhs.iter().enumerate() {
+        buf[i].im = *v;
+    }
+
+    fft(&mut buf, false);
+
+    // With z = a + ib, the product of the spectra of a and b equals (Z[k]^2 - conj(Z[n-k])^2) / 4i.
+    let mut prod = vec![Complex::new(0.0, 0.0); n];
+    for k in 0..n {
+        let z = buf[k];
+        let zc = buf[(n - k) % n].conj();
+        prod[k] = (z * z - zc * zc) * Complex::new(0.0, -0.25);
+    }
+
+    fft(&mut prod, true);
+
+    prod.into_iter().take(out_len).map(|c| c.re).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_naive() {
+        let a: Vec<f64> = (0..37).map(|i| (i as f64 * 0.37).sin().abs()).collect();
+        let b: Vec<f64> = (0..19).map(|i| (i as f64 * 1.3).cos().abs()).collect();
+
+        let mut naive = vec![0.0; a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                naive[i + j] += x * y;
+            }
+        }
+
+        let fast = convolve_real(&a, &b);
+        assert_eq!(fast.len(), naive.len());
+        for (f, n) in fast.iter().zip(naive.iter()) {
+            assert!((f - n).abs() < 1e-10);
+        }
+    }
+}
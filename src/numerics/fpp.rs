@@ -10,7 +10,7 @@ type Fpnum = usize;
 /// A fixed point probability type.
 /// Value is stored as an integer, representing a probability of value/integer::MAX.
 /// Should in theory be more efficient than using floating point numbers.
-#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Fpp(Fpnum);
 
 impl Fpp {
@@ -23,6 +23,52 @@ impl Fpp {
         self.0
     }
 
+    /// The probability inner / MAX.
+    pub fn from_inner(inner: Fpnum) -> Fpp {
+        Fpp(inner)
+    }
+
+    /// Addition that returns None instead of exceeding 1.
+    pub fn checked_add(self, rhs: Fpp) -> Option<Fpp> {
+        self.0.checked_add(rhs.0).map(Fpp)
+    }
+
+    /// Addition that is clamped to 1.
+    pub fn saturating_add(self, rhs: Fpp) -> Fpp {
+        Fpp(self.0.saturating_add(rhs.0))
+    }
+
+    /// Subtraction that returns None instead of going below 0.
+    pub fn checked_sub(self, rhs: Fpp) -> Option<Fpp> {
+        self.0.checked_sub(rhs.0).map(Fpp)
+    }
+
+    /// Subtraction that is clamped to 0.
+    pub fn saturating_sub(self, rhs: Fpp) -> Fpp {
+        Fpp(self.0.saturating_sub(rhs.0))
+    }
+
+    /// Division that returns None when dividing by zero, or when the result exceeds 1.
+    pub fn checked_div(self, rhs: Fpp) -> Option<Fpp> {
+        if rhs.0 == 0 {
+            return None;
+        }
+        let max = Fpnum::MAX as u128;
+        let divisor = rhs.0 as u128;
+        let quotient = self.0 as u128 * max / divisor;
+        Fpnum::try_from(quotient).ok().map(Fpp)
+    }
+
+    /// Division that is clamped to 1, including when dividing by zero.
+    /// 0 / 0 is defined as 0.
+    pub fn saturating_div(self, rhs: Fpp) -> Fpp {
+        match self.checked_div(rhs) {
+            Some(result) => result,
+            None if self.0 == 0 => Fpp::MIN,
+            None => Fpp::MAX,
+        }
+    }
+
     /// Returns the valid range of values for a given numeric type.
     fn bounds<T: Num>() -> RangeInclusive<T> {
         T::zero()..=T::one()
@@ -53,36 +99,55 @@ impl<T: Num + ToPrimitive + PartialOrd> ToFpp for T {
 }
 
 // Operator implementations for Fpp.
+/// # Panics
+/// Panics when the sum exceeds 1.
+/// Use `checked_add` or `saturating_add` to avoid this.
 impl std::ops::Add for Fpp {
     type Output = Fpp;
 
     fn add(self, rhs: Fpp) -> Fpp {
-        Fpp(self.0 + rhs.0)
+        self.checked_add(rhs)
+            .expect("Fpp addition with a result above 1.")
     }
 }
 
+/// # Panics
+/// Panics when the difference is below 0.
+/// Use `checked_sub` or `saturating_sub` to avoid this.
 impl std::ops::Sub for Fpp {
     type Output = Fpp;
 
     fn sub(self, rhs: Fpp) -> Fpp {
-        Fpp(self.0 - rhs.0)
+        self.checked_sub(rhs)
+            .expect("Fpp subtraction with a result below 0.")
     }
 }
 
+/// Multiplication is performed using a 128-bit intermediate, and rounds down.
+/// It can never overflow, as the product of two probabilities is at most either of them.
+/// Rounding down means that sums of products never exceed the product of the sums, which keeps convolutions within 1.
 impl std::ops::Mul for Fpp {
     type Output = Fpp;
 
     fn mul(self, rhs: Fpp) -> Fpp {
-        Fpp((self.0 * rhs.0) / Fpnum::MAX)
+        let max = Fpnum::MAX as u128;
+        let product = self.0 as u128 * rhs.0 as u128;
+        Fpp((product / max) as Fpnum)
     }
 }
 
-// Division is imprecise, but is here for completeness.
+/// Division is performed using a 128-bit intermediate, and rounds down,
+/// so probabilities divided by their total never sum to more than 1.
+///
+/// # Panics
+/// Panics when dividing by zero, or when the result exceeds 1.
+/// Use `checked_div` or `saturating_div` to avoid this.
 impl std::ops::Div for Fpp {
     type Output = Fpp;
 
     fn div(self, rhs: Fpp) -> Fpp {
-        Fpp((self.0 * Fpnum::MAX) / rhs.0)
+        self.checked_div(rhs)
+            .expect("Fpp division by zero or with a result above 1.")
     }
 }
 
@@ -94,6 +159,15 @@ impl std::ops::Rem for Fpp {
     }
 }
 
+forward_ops!(
+    Fpp;
+    Add::add, AddAssign::add_assign;
+    Sub::sub, SubAssign::sub_assign;
+    Mul::mul, MulAssign::mul_assign;
+    Div::div, DivAssign::div_assign;
+    Rem::rem, RemAssign::rem_assign;
+);
+
 // Num trait implementations for Fpp.
 impl One for Fpp {
     fn one() -> Fpp {
@@ -119,24 +193,103 @@ impl Num for Fpp {
     }
 }
 
+/// Conversion to integers truncates, like it does for floats.
+/// This means only a probability of exactly 1 converts to 1.
 impl ToPrimitive for Fpp {
     fn to_i64(&self) -> Option<i64> {
-        if self.0 == Fpnum::MAX {
-            Some(1)
-        } else {
-            Some(0)
-        }
+        Some((self.0 == Fpnum::MAX) as i64)
     }
 
     fn to_u64(&self) -> Option<u64> {
-        if self.0 == Fpnum::MAX {
-            Some(1)
-        } else {
-            Some(0)
-        }
+        Some((self.0 == Fpnum::MAX) as u64)
     }
 
     fn to_f64(&self) -> Option<f64> {
         Some(self.0 as f64 / Fpnum::MAX as f64)
     }
 }
+
+/// Only 0 and 1 can be converted from integers, other values are not valid probabilities.
+impl FromPrimitive for Fpp {
+    fn from_i64(n: i64) -> Option<Fpp> {
+        match n {
+            0 => Some(Fpp::MIN),
+            1 => Some(Fpp::MAX),
+            _ => None,
+        }
+    }
+
+    fn from_u64(n: u64) -> Option<Fpp> {
+        Self::from_i64(n.try_into().ok()?)
+    }
+
+    fn from_f64(n: f64) -> Option<Fpp> {
+        n.to_fpp().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigUint;
+
+    /// A spread of values, including the extremes.
+    fn samples() -> Vec<Fpp> {
+        let mut values = vec![Fpp::MIN, Fpp(1), Fpp(2), Fpp::MAX, Fpp(Fpnum::MAX - 1)];
+        let mut x: Fpnum = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..100 {
+            // Simple xorshift to get some pseudo-random values
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            values.push(Fpp(x));
+        }
+        values
+    }
+
+    #[test]
+    fn mul_rounds_down() {
+        let max = BigUint::from(Fpnum::MAX);
+        for a in samples() {
+            for b in samples() {
+                let exact = BigUint::from(a.0) * BigUint::from(b.0) / &max;
+                assert_eq!(BigUint::from((a * b).0), exact);
+
+                let float = a.to_f64().unwrap() * b.to_f64().unwrap();
+                assert!(((a * b).to_f64().unwrap() - float).abs() < 1e-15);
+            }
+        }
+    }
+
+    #[test]
+    fn div_rounds_down() {
+        let max = BigUint::from(Fpnum::MAX);
+        for a in samples() {
+            for b in samples() {
+                match a.checked_div(b) {
+                    Some(result) => {
+                        let exact = BigUint::from(a.0) * &max / BigUint::from(b.0);
+                        assert_eq!(BigUint::from(result.0), exact);
+                        assert_eq!(a.saturating_div(b), result);
+                    }
+                    None => {
+                        // Division by zero, or a result above 1
+                        assert!(b.0 == 0 || a.0 > b.0);
+                        let clamped = if a.0 == 0 { Fpp::MIN } else { Fpp::MAX };
+                        assert_eq!(a.saturating_div(b), clamped);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn checked_add_sub() {
+        let half = 0.5.to_fpp().unwrap();
+        assert_eq!(Fpp::MAX.checked_add(half), None);
+        assert_eq!(Fpp::MAX.saturating_add(half), Fpp::MAX);
+        assert_eq!(Fpp::MIN.checked_sub(half), None);
+        assert_eq!(Fpp::MIN.saturating_sub(half), Fpp::MIN);
+        assert_eq!(half.checked_sub(half), Some(Fpp::MIN));
+    }
+}
@@ -0,0 +1,180 @@
+use num::traits::*;
+use num::ToPrimitive;
+
+/// A probability stored as its natural logarithm.
+/// Capable of expressing probabilities far too small for an f64, like rolling all sixes on 500d6.
+///
+/// Multiplication becomes addition of logarithms, and addition uses the log-sum-exp trick to stay accurate.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct LogProb(f64);
+
+impl LogProb {
+    /// Construct a LogProb from the natural logarithm of a probability.
+    pub fn from_ln(ln: f64) -> LogProb {
+        LogProb(ln)
+    }
+
+    /// The natural logarithm of the probability.
+    pub fn ln(&self) -> f64 {
+        self.0
+    }
+}
+
+// Operator implementations for LogProb.
+/// ln(e^a + e^b), computed without leaving log space.
+impl std::ops::Add for LogProb {
+    type Output = LogProb;
+
+    fn add(self, rhs: LogProb) -> LogProb {
+        let (hi, lo) = if self.0 >= rhs.0 {
+            (self.0, rhs.0)
+        } else {
+            (rhs.0, self.0)
+        };
+        if lo == f64::NEG_INFINITY {
+            return LogProb(hi);
+        }
+        LogProb(hi + (lo - hi).exp().ln_1p())
+    }
+}
+
+/// ln(e^a - e^b), which saturates at 0 (ln = -inf) when the rhs is at least as large.
+impl std::ops::Sub for LogProb {
+    type Output = LogProb;
+
+    fn sub(self, rhs: LogProb) -> LogProb {
+        if rhs.0 == f64::NEG_INFINITY {
+            return self;
+        }
+        if rhs.0 >= self.0 {
+            return LogProb::zero();
+        }
+        LogProb(self.0 + (-(rhs.0 - self.0).exp_m1()).ln())
+    }
+}
+
+impl std::ops::Mul for LogProb {
+    type Output = LogProb;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: LogProb) -> LogProb {
+        // -inf + inf would be NaN, but anything times 0 is 0
+        if self.is_zero() || rhs.is_zero() {
+            return LogProb::zero();
+        }
+        LogProb(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::Div for LogProb {
+    type Output = LogProb;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: LogProb) -> LogProb {
+        LogProb(self.0 - rhs.0)
+    }
+}
+
+// The remainder is meaningless for probabilities, but is required by Num.
+impl std::ops::Rem for LogProb {
+    type Output = LogProb;
+
+    fn rem(self, rhs: LogProb) -> LogProb {
+        LogProb((self.0.exp() % rhs.0.exp()).ln())
+    }
+}
+
+forward_ops!(
+    LogProb;
+    Add::add, AddAssign::add_assign;
+    Sub::sub, SubAssign::sub_assign;
+    Mul::mul, MulAssign::mul_assign;
+    Div::div, DivAssign::div_assign;
+    Rem::rem, RemAssign::rem_assign;
+);
+
+// Num trait implementations for LogProb.
+impl One for LogProb {
+    fn one() -> LogProb {
+        LogProb(0.0)
+    }
+}
+
+impl Zero for LogProb {
+    fn zero() -> LogProb {
+        LogProb(f64::NEG_INFINITY)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == f64::NEG_INFINITY
+    }
+}
+
+/// Parses a regular (not logarithmic) probability.
+impl Num for LogProb {
+    type FromStrRadixErr = <f64 as Num>::FromStrRadixErr;
+
+    fn from_str_radix(str: &str, radix: u32) -> Result<LogProb, Self::FromStrRadixErr> {
+        <f64 as Num>::from_str_radix(str, radix).map(|p| LogProb(p.ln()))
+    }
+}
+
+/// Conversions leave log space, so tiny probabilities become 0.
+impl ToPrimitive for LogProb {
+    fn to_i64(&self) -> Option<i64> {
+        self.to_f64()?.to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.to_f64()?.to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(self.0.exp())
+    }
+}
+
+/// Negative numbers have no logarithm, so they cannot be converted.
+impl FromPrimitive for LogProb {
+    fn from_i64(n: i64) -> Option<LogProb> {
+        Self::from_f64(n as f64)
+    }
+
+    fn from_u64(n: u64) -> Option<LogProb> {
+        Self::from_f64(n as f64)
+    }
+
+    fn from_f64(n: f64) -> Option<LogProb> {
+        (n >= 0.0).then(|| LogProb(n.ln()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lp(p: f64) -> LogProb {
+        LogProb::from_f64(p).unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert!(((lp(0.25) + lp(0.5)).to_f64().unwrap() - 0.75).abs() < 1e-15);
+        assert!(((lp(0.75) - lp(0.5)).to_f64().unwrap() - 0.25).abs() < 1e-15);
+        assert!(((lp(0.25) * lp(0.5)).to_f64().unwrap() - 0.125).abs() < 1e-15);
+        assert!(((lp(0.25) / lp(0.5)).to_f64().unwrap() - 0.5).abs() < 1e-15);
+        assert_eq!(lp(0.25) - lp(0.5), LogProb::zero());
+        assert_eq!(LogProb::zero() * LogProb::zero(), LogProb::zero());
+        assert_eq!(LogProb::zero() + lp(0.5), lp(0.5));
+        assert_eq!(LogProb::from_f64(-1.0), None);
+    }
+
+    #[test]
+    fn no_underflow() {
+        // Far below the smallest f64
+        let tiny = num::pow(lp(1.0 / 6.0), 1000);
+        assert_eq!(tiny.to_f64(), Some(0.0));
+        assert!((tiny.ln() + 1000.0 * 6f64.ln()).abs() < 1e-9);
+        assert!(((tiny + tiny).ln() - tiny.ln() - 2f64.ln()).abs() < 1e-9);
+    }
+}
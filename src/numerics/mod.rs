@@ -1,11 +1,42 @@
 //! Numeric types one might want to use to represent probabilities.
 //!
-//! Currently, only Fpp is implemented.
-//! Ideas to implement later:
-//! - A logarithmic type capable of expressing infinitesimally small probabilities
+//! Currently implemented:
+//! - Fpp, a fixed point probability type
+//! - LogProb, a logarithmic type capable of expressing infinitesimally small probabilities
 //!  
 
+/// Implements the reference and assignment versions of operators in terms of the by-value versions.
+macro_rules! forward_ops {
+    ($t:ty; $($op:ident::$method:ident, $assign:ident::$assign_method:ident);* $(;)?) => {
+        $(
+            impl std::ops::$op<&$t> for $t {
+                type Output = $t;
+
+                fn $method(self, rhs: &$t) -> $t {
+                    std::ops::$op::$method(self, *rhs)
+                }
+            }
+
+            impl std::ops::$assign for $t {
+                fn $assign_method(&mut self, rhs: $t) {
+                    *self = std::ops::$op::$method(*self, rhs);
+                }
+            }
+
+            impl std::ops::$assign<&$t> for $t {
+                fn $assign_method(&mut self, rhs: &$t) {
+                    *self = std::ops::$op::$method(*self, *rhs);
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) mod fft;
 mod fpp;
+mod log_prob;
+pub(crate) mod ntt;
 
 pub use fpp::Fpp;
 pub use fpp::ToFpp;
+pub use log_prob::LogProb;
@@ -0,0 +1,286 @@
+use std::sync::OnceLock;
+
+use num::{BigUint, ToPrimitive, Zero};
+
+/// Every prime used is of the form c·2^MAX_LOG_LEN + 1, so transforms up to this length are supported.
+const MAX_LOG_LEN: u32 = 23;
+
+/// A prime suitable for the NTT, along with one of its primitive roots.
+struct NttPrime {
+    p: u64,
+    root: u64,
+}
+
+fn mod_pow(mut base: u64, mut exp: u64, p: u64) -> u64 {
+    let mut result = 1;
+    base %= p;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % p;
+        }
+        base = base * base % p;
+        exp >>= 1;
+    }
+    result
+}
+
+fn mod_inv(x: u64, p: u64) -> u64 {
+    mod_pow(x, p - 2, p)
+}
+
+fn is_prime(n: u64) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|d| d * d <= n)
+            .all(|d| !n.is_multiple_of(d))
+}
+
+/// Finds the smallest primitive root of p, given that p - 1 = c·2^MAX_LOG_LEN.
+fn primitive_root(p: u64) -> u64 {
+    let c = (p - 1) >> MAX_LOG_LEN;
+    let factors: Vec<u64> = [2]
+        .into_iter()
+        .chain((3..=c).filter(|q| c.is_multiple_of(*q) && is_prime(*q)))
+        .collect();
+    (2..p)
+        .find(|g| factors.iter().all(|q| mod_pow(*g, (p - 1) / q, p) != 1))
+        .expect("Every prime has a primitive root.")
+}
+
+/// All NTT primes below 2^31, largest first.
+/// Keeping them below 2^31 ensures that products of residues never overflow a u64.
+fn primes() -> &'static [NttPrime] {
+    static PRIMES: OnceLock<Vec<NttPrime>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        (1..(1u64 << (31 - MAX_LOG_LEN)))
+            .rev()
+            .map(|c| (c << MAX_LOG_LEN) + 1)
+            .filter(|p| is_prime(*p))
+            .map(|p| NttPrime {
+                p,
+                root: primitive_root(p),
+            })
+            .collect()
+    })
+}
+
+/// Returns the number of primes needed for their product to exceed 2^bits, if there are enough primes.
+fn primes_needed(bits: u64) -> Option<usize> {
+    let mut total = 0;
+    for (i, prime) in primes().iter().enumerate() {
+        // Every prime is above 2^30
+        total += prime.p.ilog2() as u64;
+        if total > bits {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// In-place iterative number theoretic transform modulo a prime.
+fn ntt(buf: &mut [u64], prime: &NttPrime, inverse: bool) {
+    let n = buf.len();
+    let p = prime.p;
+    debug_assert!(n.is_power_of_two() && n.ilog2() <= MAX_LOG_LEN);
+
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+
+    // Butterflies
+    let mut len = 2;
+    while len <= n {
+        let mut step = mod_pow(prime.root, (p - 1) / len as u64, p);
+        if inverse {
+            step = mod_inv(step, p);
+        }
+        for chunk in buf.chunks_exact_mut(len) {
+            let (lo, hi) = chunk.split_at_mut(len / 2);
+            let mut w = 1;
+            for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+                let t = *b * w % p;
+                *b = (*a + p - t) % p;
+                *a = (*a + t) % p;
+                w = w * step % p;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = mod_inv(n as u64, p);
+        for v in buf.iter_mut() {
+            *v = *v * scale % p;
+        }
+    }
+}
+
+/// Convolves both sequences modulo the first k primes,
+/// and returns the mixed-radix digits (Garner's algorithm) of every entry of the result.
+///
+/// Entry i of the result equals d[0] + d[1]·p[0] + d[2]·p[0]·p[1] + ..., where d are its digits.
+fn convolve_digits<X>(
+    lhs: &[X],
+    rhs: &[X],
+    k: usize,
+    residue: impl Fn(&X, u64) -> u64,
+) -> Vec<Vec<u64>> {
+    let out_len = lhs.len() + rhs.len() - 1;
+    let n = out_len.next_power_of_two();
+    let primes = &primes()[..k];
+
+    // Convolution modulo every prime
+    let residues: Vec<Vec<u64>> = primes
+        .iter()
+        .map(|prime| {
+            let mut a = vec![0; n];
+            let mut b = vec![0; n];
+            for (dst, src) in a.iter_mut().zip(lhs) {
+                *dst = residue(src, prime.p);
+            }
+            for (dst, src) in b.iter_mut().zip(rhs) {
+                *dst = residue(src, prime.p);
+            }
+            ntt(&mut a, prime, false);
+            ntt(&mut b, prime, false);
+            for (x, y) in a.iter_mut().zip(b.iter()) {
+                *x = *x * y % prime.p;
+            }
+            ntt(&mut a, prime, true);
+            a
+        })
+        .collect();
+
+    // inverses[j][i] = p[j]^-1 mod p[i]
+    let inverses: Vec<Vec<u64>> = primes
+        .iter()
+        .map(|pj| primes.iter().map(|pi| mod_inv(pj.p % pi.p, pi.p)).collect())
+        .collect();
+
+    // Garner's algorithm
+    (0..out_len)
+        .map(|idx| {
+            let mut digits: Vec<u64> = Vec::with_capacity(k);
+            for i in 0..k {
+                let p = primes[i].p;
+                let mut x = residues[i][idx];
+                for (j, d) in digits.iter().enumerate() {
+                    x = (x + p - d % p) % p * inverses[j][i] % p;
+                }
+                digits.push(x);
+            }
+            digits
+        })
+        .collect()
+}
+
+/// Checks whether a transform for the given input lengths fits within the supported length.
+fn supported_len(lhs: usize, rhs: usize) -> bool {
+    lhs > 0 && rhs > 0 && (lhs + rhs - 1).next_power_of_two() <= 1 << MAX_LOG_LEN
+}
+
+/// Number of bits needed to hold any entry of the convolution, given the maximum bits of both inputs.
+fn result_bits(lhs_bits: u64, rhs_bits: u64, lhs_len: usize, rhs_len: usize) -> u64 {
+    lhs_bits + rhs_bits + (lhs_len.min(rhs_len) as u64).ilog2() as u64 + 1
+}
+
+/// Computes the exact linear convolution of two sequences of unsigned integers.
+///
+/// Returns None when the result cannot be guaranteed to fit in a u128, or the inputs are too long.
+pub(crate) fn convolve_u64(lhs: &[u64], rhs: &[u64]) -> Option<Vec<u128>> {
+    if !supported_len(lhs.len(), rhs.len()) {
+        return None;
+    }
+    let bits = |s: &[u64]| 64 - s.iter().max().unwrap().leading_zeros() as u64;
+    let k = primes_needed(result_bits(bits(lhs), bits(rhs), lhs.len(), rhs.len()))?;
+    if primes()[..k].iter().map(|p| p.p.ilog2() + 1).sum::<u32>() > 128 {
+        return None;
+    }
+
+    let primes = primes();
+    Some(
+        convolve_digits(lhs, rhs, k, |x, p| x % p)
+            .into_iter()
+            .map(|digits| {
+                digits
+                    .iter()
+                    .zip(primes)
+                    .rev()
+                    .fold(0u128, |acc, (d, prime)| acc * prime.p as u128 + *d as u128)
+            })
+            .collect(),
+    )
+}
+
+/// Computes the exact linear convolution of two sequences of arbitrarily large unsigned integers.
+///
+/// Returns None when the inputs are too long, or the result is too large to be reconstructed.
+pub(crate) fn convolve_biguint(lhs: &[BigUint], rhs: &[BigUint]) -> Option<Vec<BigUint>> {
+    if !supported_len(lhs.len(), rhs.len()) {
+        return None;
+    }
+    let bits = |s: &[BigUint]| s.iter().map(|x| x.bits()).max().unwrap();
+    let k = primes_needed(result_bits(bits(lhs), bits(rhs), lhs.len(), rhs.len()))?;
+
+    let primes = primes();
+    let residue = |x: &BigUint, p: u64| (x % p).to_u64().expect("Residue is below p.");
+    Some(
+        convolve_digits(lhs, rhs, k, residue)
+            .into_iter()
+            .map(|digits| {
+                digits
+                    .iter()
+                    .zip(primes)
+                    .rev()
+                    .fold(BigUint::zero(), |acc, (d, prime)| acc * prime.p + *d)
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive(lhs: &[u64], rhs: &[u64]) -> Vec<u128> {
+        let mut out = vec![0u128; lhs.len() + rhs.len() - 1];
+        for (i, a) in lhs.iter().enumerate() {
+            for (j, b) in rhs.iter().enumerate() {
+                out[i + j] += *a as u128 * *b as u128;
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn exact_u64() {
+        let a: Vec<u64> = (0..100).map(|i| (i * 7919 + 13) % 100_003).collect();
+        let b: Vec<u64> = (0..57).map(|i| u32::MAX as u64 - i * 31).collect();
+        assert_eq!(convolve_u64(&a, &b), Some(naive(&a, &b)));
+    }
+
+    #[test]
+    fn exact_biguint() {
+        // Powers of 3 easily exceed any fixed size integer
+        let a: Vec<BigUint> = (0..40u32).map(|i| BigUint::from(3u32).pow(i * 5)).collect();
+        let b: Vec<BigUint> = (0..30u32).map(|i| BigUint::from(i + 1)).collect();
+
+        let mut expected = vec![BigUint::zero(); a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                expected[i + j] += x * y;
+            }
+        }
+        assert_eq!(convolve_biguint(&a, &b), Some(expected));
+    }
+}
@@ -0,0 +1,42 @@
+use std::ops::Range;
+
+use crate::Sample;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// Which dice of a roll to keep.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keep {
+    Highest(usize),
+    Lowest(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
+    Constant(Sample),
+    /// NdX, optionally keeping only some of the dice.
+    Dice {
+        count: usize,
+        sides: Sample,
+        keep: Option<Keep>,
+    },
+    Neg(Box<Expr>),
+    Binary {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+}
+
+/// A node in the syntax tree, along with the part of the input it was parsed from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Range<usize>,
+}
@@ -0,0 +1,33 @@
+use crate::{DivisionPolicy, LlDoiceError, Number, PDF};
+
+use super::ast::{BinOp, Expr, ExprKind, Keep};
+
+/// Evaluate a syntax tree into the distribution of its result.
+///
+/// Arithmetic is checked, so dividing by a roll that can be 0 or overflowing an outcome
+/// returns an error with the span of the offending expression, instead of panicking.
+pub fn eval<T: Number>(expr: &Expr) -> Result<PDF<T, true>, LlDoiceError> {
+    let result = match &expr.kind {
+        ExprKind::Constant(value) => Ok(PDF::default().offset(*value)),
+        ExprKind::Dice { count, sides, keep } => {
+            let die = PDF::die(*sides);
+            Ok(match keep {
+                None => PDF::ntimes(*count, &die),
+                Some(Keep::Highest(n)) => die.keep_highest(*count, *n),
+                Some(Keep::Lowest(n)) => die.keep_lowest(*count, *n),
+            })
+        }
+        ExprKind::Neg(inner) => eval::<T>(inner)?.checked_neg(),
+        ExprKind::Binary { op, lhs, rhs } => {
+            let lhs = eval::<T>(lhs)?;
+            let rhs = eval::<T>(rhs)?;
+            match op {
+                BinOp::Add => lhs.checked_add(&rhs),
+                BinOp::Sub => lhs.checked_sub(&rhs),
+                BinOp::Mul => lhs.checked_mul(&rhs),
+                BinOp::Div => lhs.checked_div(&rhs, DivisionPolicy::default()),
+            }
+        }
+    };
+    result.map_err(|e| e.with_span(&expr.span))
+}
@@ -0,0 +1,113 @@
+use std::ops::Range;
+
+use crate::{LlDoiceError, Sample};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Number(Sample),
+    /// The 'd' in NdX.
+    Dice,
+    /// 'd%', which is shorthand for 'd100'.
+    Percentile,
+    /// 'kh', or simply 'k'.
+    KeepHighest,
+    /// 'kl'.
+    KeepLowest,
+    /// 'dh'.
+    DropHighest,
+    /// 'dl'.
+    DropLowest,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LParen,
+    RParen,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// Splits a dice expression into tokens, skipping whitespace.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, LlDoiceError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '0'..='9' => {
+                let mut end = start + 1;
+                while let Some((i, '0'..='9')) = chars.peek() {
+                    end = i + 1;
+                    chars.next();
+                }
+                let value = input[start..end]
+                    .parse()
+                    .map_err(|_| LlDoiceError::InvalidNumber { span: start..end })?;
+                tokens.push(Token {
+                    kind: TokenKind::Number(value),
+                    span: start..end,
+                });
+                continue;
+            }
+            'd' | 'D' => {
+                let kind = match chars.peek() {
+                    Some((_, '%')) => TokenKind::Percentile,
+                    Some((_, 'h' | 'H')) => TokenKind::DropHighest,
+                    Some((_, 'l' | 'L')) => TokenKind::DropLowest,
+                    _ => TokenKind::Dice,
+                };
+                if kind != TokenKind::Dice {
+                    chars.next();
+                    tokens.push(Token {
+                        kind,
+                        span: start..start + 2,
+                    });
+                    continue;
+                }
+                kind
+            }
+            'k' | 'K' => match chars.peek() {
+                Some((_, 'h' | 'H')) => {
+                    chars.next();
+                    tokens.push(Token {
+                        kind: TokenKind::KeepHighest,
+                        span: start..start + 2,
+                    });
+                    continue;
+                }
+                Some((_, 'l' | 'L')) => {
+                    chars.next();
+                    tokens.push(Token {
+                        kind: TokenKind::KeepLowest,
+                        span: start..start + 2,
+                    });
+                    continue;
+                }
+                _ => TokenKind::KeepHighest,
+            },
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            character => {
+                return Err(LlDoiceError::UnexpectedCharacter {
+                    character,
+                    span: start..start + character.len_utf8(),
+                })
+            }
+        };
+        tokens.push(Token {
+            kind,
+            span: start..start + c.len_utf8(),
+        });
+    }
+
+    Ok(tokens)
+}
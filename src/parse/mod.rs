@@ -0,0 +1,143 @@
+//! Parsing of dice expressions like `4d6kh3 + 2` into PDFs.
+//!
+//! Supported syntax:
+//! - Constants: `3`
+//! - Dice: `d20`, `3d8`, `d%` (shorthand for `d100`)
+//! - Keeping the highest or lowest dice: `4d6kh3`, `2d20kl1` (`k` is shorthand for `kh`)
+//! - Dropping the highest or lowest dice: `4d6dl1`, `3d6dh1`
+//! - Arithmetic: `+`, `-`, `*`, `/` and parentheses, with the usual precedence
+//!
+//! A roll can have at most `MAX_DICE` dice with at most `MAX_SIDES` sides each,
+//! and at most `MAX_KEEP_FACES` dice times sides when keeping or dropping dice.
+//!
+//! Parsing happens in three stages: the input is split into tokens, the tokens are parsed into a syntax tree,
+//! and the syntax tree is evaluated into a `PDF`.
+
+pub mod ast;
+mod eval;
+pub mod lexer;
+mod parser;
+
+pub use eval::eval;
+pub use parser::{MAX_DICE, MAX_KEEP_FACES, MAX_SIDES};
+
+use crate::{LlDoiceError, Number, PDF};
+
+/// Parse a dice expression into its syntax tree.
+pub fn parse_expr(input: &str) -> Result<ast::Expr, LlDoiceError> {
+    let tokens = lexer::tokenize(input)?;
+    parser::parse_tokens(&tokens, input.len())
+}
+
+/// Parse a dice expression and compute the distribution of its result.
+///
+/// Dividing by a roll that can be 0, or an outcome that does not fit in a `Sample`,
+/// is reported as an error along with where it happened.
+pub fn parse<T: Number>(input: &str) -> Result<PDF<T, true>, LlDoiceError> {
+    eval(&parse_expr(input)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_data_close;
+
+    fn assert_close(a: &PDF<f64, true>, b: &PDF<f64, true>) {
+        assert_data_close(a.data(), b.data());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let d8 = PDF::<f64, true>::die(8);
+        let d6 = PDF::<f64, true>::die(6);
+        let expected = (&PDF::ntimes(3, &d8) + &d6).offset(-1);
+        assert_close(&parse("3d8+1d6-1").unwrap(), &expected);
+
+        // Multiplication binds tighter than addition
+        assert_close(
+            &parse("2 * d6 + 1").unwrap(),
+            &parse("(2 * d6) + 1").unwrap(),
+        );
+        assert_close(&parse("-d4").unwrap(), &PDF::die(4).scale(-1));
+    }
+
+    #[test]
+    fn keep() {
+        let mut adv = PDF::<f64, true>::die(20);
+        adv.with_advantage(1);
+        assert_close(&parse("2d20kh1").unwrap(), &adv);
+        assert_close(&parse("2d20k").unwrap(), &adv);
+
+        // Keeping the lowest of a d20 is the same as 21 minus the highest
+        let dis = parse::<f64>("2d20kl1").unwrap();
+        assert_close(&dis, &adv.scale(-1).offset(21));
+
+        let d6 = PDF::<f64, true>::die(6);
+        assert_close(&parse("4d6kh3").unwrap(), &d6.drop_lowest(4, 1));
+        assert_close(&parse("4d6dl1").unwrap(), &d6.keep_highest(4, 3));
+        assert_close(&parse("3d6dh1").unwrap(), &d6.keep_lowest(3, 2));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_expr("2d6 + x"),
+            Err(LlDoiceError::UnexpectedCharacter {
+                character: 'x',
+                span: 6..7
+            })
+        );
+        assert_eq!(
+            parse_expr("2d6 +"),
+            Err(LlDoiceError::UnexpectedEnd { span: 5..5 })
+        );
+        assert_eq!(
+            parse_expr("(d6 2"),
+            Err(LlDoiceError::UnexpectedToken { span: 4..5 })
+        );
+        assert!(matches!(
+            parse_expr("4d6dl4"),
+            Err(LlDoiceError::InvalidDice { .. })
+        ));
+        assert!(matches!(
+            parse_expr("2d6kh3"),
+            Err(LlDoiceError::InvalidDice { span, .. }) if span == (0..6)
+        ));
+    }
+
+    #[test]
+    fn limits() {
+        assert!(parse_expr("100d1000").is_ok());
+        assert!(parse_expr("100d20kh50").is_ok());
+        for input in ["1000000000d1000000000", "101d6", "d1001", "100d21kh50"] {
+            assert!(
+                matches!(parse_expr(input), Err(LlDoiceError::InvalidDice { .. })),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn evaluation_errors() {
+        assert_eq!(
+            parse::<f64>("d6/0"),
+            Err(LlDoiceError::DivisionByZero {
+                probability: 1.0,
+                span: Some(0..4)
+            })
+        );
+        assert!(matches!(
+            parse::<f64>("1 + d6/(d3-2)"),
+            Err(LlDoiceError::DivisionByZero { probability, span })
+                if (probability - 1.0 / 3.0).abs() < 1e-12 && span == Some(4..13)
+        ));
+        assert_eq!(
+            parse::<f64>("d6*4611686018427387904"),
+            Err(LlDoiceError::OutcomeOverflow { span: Some(0..22) })
+        );
+        assert_eq!(
+            parse::<f64>("-(4611686018427387904 + 4611686018427387904)"),
+            Err(LlDoiceError::OutcomeOverflow { span: Some(1..44) })
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Recursive descent parser for dice expressions.
+//!
+//! Grammar, from lowest to highest precedence:
+//! ```text
+//! expr  := term (('+' | '-') term)*
+//! term  := unary (('*' | '/') unary)*
+//! unary := '-' unary | atom
+//! atom  := dice | number | '(' expr ')'
+//! dice  := number? ('d' number | 'd%') (('kh' | 'kl' | 'dh' | 'dl') number?)?
+//! ```
+
+use std::ops::Range;
+
+use crate::{LlDoiceError, Sample};
+
+use super::ast::{BinOp, Expr, ExprKind, Keep};
+use super::lexer::{Token, TokenKind};
+
+/// The most dice a single roll can have, so that user input cannot make evaluation hang.
+pub const MAX_DICE: Sample = 100;
+/// The most sides a die can have.
+pub const MAX_SIDES: Sample = 1000;
+/// The most faces, dice times sides, a roll that keeps or drops dice can have,
+/// as keeping dice is much slower to evaluate than summing them.
+pub const MAX_KEEP_FACES: Sample = 2000;
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    /// Length of the input, used to report errors at the end of it.
+    end: usize,
+}
+
+/// Parse a list of tokens into a syntax tree.
+pub fn parse_tokens(tokens: &[Token], input_len: usize) -> Result<Expr, LlDoiceError> {
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: input_len,
+    };
+    let expr = parser.expr()?;
+    match parser.peek() {
+        Some(token) => Err(LlDoiceError::UnexpectedToken {
+            span: token.span.clone(),
+        }),
+        None => Ok(expr),
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_kind(&self) -> Option<TokenKind> {
+        self.peek().map(|t| t.kind)
+    }
+
+    fn next(&mut self) -> Result<Token, LlDoiceError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or(LlDoiceError::UnexpectedEnd {
+                span: self.end..self.end,
+            })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn binary(op: BinOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr {
+            span: lhs.span.start..rhs.span.end,
+            kind: ExprKind::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, LlDoiceError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek_kind() {
+                Some(TokenKind::Plus) => BinOp::Add,
+                Some(TokenKind::Minus) => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = Self::binary(op, lhs, rhs);
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, LlDoiceError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek_kind() {
+                Some(TokenKind::Star) => BinOp::Mul,
+                Some(TokenKind::Slash) => BinOp::Div,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.unary()?;
+            lhs = Self::binary(op, lhs, rhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, LlDoiceError> {
+        if self.peek_kind() == Some(TokenKind::Minus) {
+            let start = self.next()?.span.start;
+            let inner = self.unary()?;
+            return Ok(Expr {
+                span: start..inner.span.end,
+                kind: ExprKind::Neg(Box::new(inner)),
+            });
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, LlDoiceError> {
+        let token = self.next()?;
+        match token.kind {
+            TokenKind::Number(count) => match self.peek_kind() {
+                Some(TokenKind::Dice | TokenKind::Percentile) => self.dice(count, token.span),
+                _ => Ok(Expr {
+                    kind: ExprKind::Constant(count),
+                    span: token.span,
+                }),
+            },
+            TokenKind::Dice | TokenKind::Percentile => {
+                // No count means a single die
+                self.pos -= 1;
+                self.dice(1, token.span)
+            }
+            TokenKind::LParen => {
+                let inner = self.expr()?;
+                let close = self.next()?;
+                if close.kind != TokenKind::RParen {
+                    return Err(LlDoiceError::UnexpectedToken { span: close.span });
+                }
+                Ok(Expr {
+                    kind: inner.kind,
+                    span: token.span.start..close.span.end,
+                })
+            }
+            _ => Err(LlDoiceError::UnexpectedToken { span: token.span }),
+        }
+    }
+
+    /// Parses the part of a dice expression after the count.
+    fn dice(&mut self, count: Sample, count_span: Range<usize>) -> Result<Expr, LlDoiceError> {
+        let start = count_span.start;
+        let token = self.next()?;
+        let (sides, mut end) = match token.kind {
+            TokenKind::Percentile => (100, token.span.end),
+            TokenKind::Dice => match self.next()? {
+                Token {
+                    kind: TokenKind::Number(sides),
+                    span,
+                } => (sides, span.end),
+                token => return Err(LlDoiceError::UnexpectedToken { span: token.span }),
+            },
+            _ => return Err(LlDoiceError::UnexpectedToken { span: token.span }),
+        };
+
+        let keep = match self.peek_kind() {
+            Some(
+                kind @ (TokenKind::KeepHighest
+                | TokenKind::KeepLowest
+                | TokenKind::DropHighest
+                | TokenKind::DropLowest),
+            ) => {
+                end = self.next()?.span.end;
+                let n = match self.peek() {
+                    Some(Token {
+                        kind: TokenKind::Number(n),
+                        span,
+                    }) => {
+                        end = span.end;
+                        let n = *n;
+                        self.pos += 1;
+                        n
+                    }
+                    _ => 1,
+                };
+                // Dropping dice is stored as keeping the rest of them
+                let keep_rest = |n: Sample| (count - n).max(0) as usize;
+                Some(match kind {
+                    TokenKind::KeepHighest => Keep::Highest(n as usize),
+                    TokenKind::KeepLowest => Keep::Lowest(n as usize),
+                    TokenKind::DropHighest => Keep::Lowest(keep_rest(n)),
+                    _ => Keep::Highest(keep_rest(n)),
+                })
+            }
+            _ => None,
+        };
+
+        let span = start..end;
+        if count < 1 {
+            return Err(LlDoiceError::InvalidDice {
+                span,
+                reason: "at least one die must be rolled",
+            });
+        }
+        if sides < 1 {
+            return Err(LlDoiceError::InvalidDice {
+                span,
+                reason: "dice must have at least one side",
+            });
+        }
+        if count > MAX_DICE {
+            return Err(LlDoiceError::InvalidDice {
+                span,
+                reason: "at most 100 dice can be rolled",
+            });
+        }
+        if sides > MAX_SIDES {
+            return Err(LlDoiceError::InvalidDice {
+                span,
+                reason: "dice can have at most 1000 sides",
+            });
+        }
+        if keep.is_some() && count * sides > MAX_KEEP_FACES {
+            return Err(LlDoiceError::InvalidDice {
+                span,
+                reason: "dice times sides can be at most 2000 when keeping or dropping dice",
+            });
+        }
+        let count = count as usize;
+        if let Some(Keep::Highest(n) | Keep::Lowest(n)) = keep {
+            if n < 1 || n > count {
+                return Err(LlDoiceError::InvalidDice {
+                    span,
+                    reason: "number of kept dice must be between one and the number of dice",
+                });
+            }
+        }
+
+        Ok(Expr {
+            kind: ExprKind::Dice { count, sides, keep },
+            span,
+        })
+    }
+}
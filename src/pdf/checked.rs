@@ -0,0 +1,348 @@
+//! Outcome arithmetic that reports division by zero and overflow instead of panicking.
+
+use std::collections::BTreeMap;
+
+use crate::LlDoiceError;
+
+use super::{Number, Sample, PDF};
+
+/// How the quotient of two outcomes is rounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// Like integer division in Rust, so -7 / 2 = -3.
+    #[default]
+    TowardZero,
+    /// Round down, so -7 / 2 = -4.
+    Floor,
+    /// Round up, so 7 / 2 = 4.
+    Ceil,
+}
+
+/// What to do with the outcomes where the divisor is 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZeroDivisor {
+    /// Return a `DivisionByZero` error.
+    #[default]
+    Error,
+    /// Drop the rolls where the divisor is 0, and renormalise the rest.
+    /// This is the same as dividing by the divisor given that it is not 0.
+    DropAndRenormalize,
+}
+
+/// Policies for dividing outcomes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DivisionPolicy {
+    pub rounding: Rounding,
+    pub zero_divisor: ZeroDivisor,
+}
+
+impl Rounding {
+    /// Divide a by b, returning None when b is 0 or the quotient overflows.
+    fn div(self, a: Sample, b: Sample) -> Option<Sample> {
+        let quotient = a.checked_div(b)?;
+        let remainder = a % b;
+        // The truncated quotient is too high when the exact one is negative, and too low when it is positive
+        let negative = (remainder < 0) != (b < 0);
+        match self {
+            Rounding::Floor if remainder != 0 && negative => quotient.checked_sub(1),
+            Rounding::Ceil if remainder != 0 && !negative => quotient.checked_add(1),
+            _ => Some(quotient),
+        }
+    }
+}
+
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    /// Like `map`, but returns an error when the transform overflows.
+    fn try_map(self, f: impl Fn(Sample) -> Option<Sample>) -> Result<Self, LlDoiceError> {
+        let mut data = BTreeMap::new();
+        for (k, v) in self.data {
+            let k = f(k).ok_or(LlDoiceError::OutcomeOverflow { span: None })?;
+            data.entry(k).and_modify(|e| *e += &v).or_insert(v);
+        }
+        Ok(PDF { data })
+    }
+
+    /// Combine every pair of outcomes of two independent rolls,
+    /// returning an error when the combination overflows.
+    fn try_combine<const OTHER: bool>(
+        &self,
+        rhs: &PDF<T, OTHER>,
+        f: impl Fn(Sample, Sample) -> Option<Sample>,
+    ) -> Result<Self, LlDoiceError> {
+        let mut data = BTreeMap::new();
+        for (outcome, prob) in self.data.iter() {
+            for (k, v) in rhs.data.iter() {
+                let combined =
+                    f(*outcome, *k).ok_or(LlDoiceError::OutcomeOverflow { span: None })?;
+                let weight = prob.clone() * v;
+                data.entry(combined)
+                    .and_modify(|e| *e += &weight)
+                    .or_insert(weight);
+            }
+        }
+        Ok(PDF { data })
+    }
+
+    /// Like `offset`, but returns an error when an outcome overflows.
+    pub fn checked_offset(self, offset: Sample) -> Result<Self, LlDoiceError> {
+        self.try_map(|k| k.checked_add(offset))
+    }
+
+    /// Like `scale`, but returns an error when an outcome overflows.
+    pub fn checked_scale(self, scale: Sample) -> Result<Self, LlDoiceError> {
+        self.try_map(|k| k.checked_mul(scale))
+    }
+
+    /// Like `divide`, but rounds according to the policy, and returns an error instead of panicking.
+    ///
+    /// Dividing by a constant 0 is always an error, as there is nothing left to renormalise.
+    pub fn checked_divide(self, divisor: Sample, rounding: Rounding) -> Result<Self, LlDoiceError> {
+        if divisor == 0 {
+            return Err(LlDoiceError::DivisionByZero {
+                probability: 1.0,
+                span: None,
+            });
+        }
+        self.try_map(|k| rounding.div(k, divisor))
+    }
+
+    /// Like the + operator, but returns an error when an outcome overflows.
+    pub fn checked_add(&self, rhs: &Self) -> Result<Self, LlDoiceError> {
+        // The sums of the extremes are the extremes of the sum, so only those need to be checked
+        let extremes = [
+            (self.data.first_key_value(), rhs.data.first_key_value()),
+            (self.data.last_key_value(), rhs.data.last_key_value()),
+        ];
+        for (a, b) in extremes {
+            if let (Some((a, _)), Some((b, _))) = (a, b) {
+                a.checked_add(*b)
+                    .ok_or(LlDoiceError::OutcomeOverflow { span: None })?;
+            }
+        }
+        Ok(self + rhs)
+    }
+
+    /// Like the - operator, but returns an error when an outcome overflows.
+    pub fn checked_sub(&self, rhs: &Self) -> Result<Self, LlDoiceError> {
+        self.checked_add(&rhs.checked_neg()?)
+    }
+
+    /// Like negation, but returns an error when an outcome is `Sample::MIN`.
+    pub fn checked_neg(&self) -> Result<Self, LlDoiceError> {
+        self.clone().try_map(Sample::checked_neg)
+    }
+
+    /// Like the * operator, but returns an error when an outcome overflows.
+    pub fn checked_mul<const OTHER: bool>(
+        &self,
+        rhs: &PDF<T, OTHER>,
+    ) -> Result<Self, LlDoiceError> {
+        self.try_combine(rhs, Sample::checked_mul)
+    }
+
+    /// Like the / operator, but divides according to the policy, and returns an error instead of panicking.
+    ///
+    /// When the divisor can be 0, the result depends on the zero divisor policy:
+    /// either an error reporting the probability of dividing by 0,
+    /// or the distribution given that the divisor is not 0.
+    pub fn checked_div<const OTHER: bool>(
+        &self,
+        rhs: &PDF<T, OTHER>,
+        policy: DivisionPolicy,
+    ) -> Result<Self, LlDoiceError> {
+        self.combine_with_divisor(rhs, policy.zero_divisor, |a, b| policy.rounding.div(a, b))
+    }
+
+    /// Like the % operator, but returns an error instead of panicking.
+    ///
+    /// A divisor that can be 0 is handled according to the zero divisor policy, like in `checked_div`.
+    pub fn checked_rem<const OTHER: bool>(
+        &self,
+        rhs: &PDF<T, OTHER>,
+        zero_divisor: ZeroDivisor,
+    ) -> Result<Self, LlDoiceError> {
+        self.combine_with_divisor(rhs, zero_divisor, Sample::checked_rem)
+    }
+
+    /// Like `modulo`, but returns an error instead of panicking.
+    pub fn checked_modulo(self, modulus: Sample) -> Result<Self, LlDoiceError> {
+        if modulus == 0 {
+            return Err(LlDoiceError::DivisionByZero {
+                probability: 1.0,
+                span: None,
+            });
+        }
+        self.try_map(|k| k.checked_rem(modulus))
+    }
+
+    /// Combine every pair of outcomes, where the rhs is a divisor that is handled according to the policy when it can be 0.
+    fn combine_with_divisor<const OTHER: bool>(
+        &self,
+        rhs: &PDF<T, OTHER>,
+        zero_divisor: ZeroDivisor,
+        f: impl Fn(Sample, Sample) -> Option<Sample>,
+    ) -> Result<Self, LlDoiceError> {
+        let zero = rhs.data.get(&0).filter(|v| !v.is_zero());
+        match (zero, zero_divisor) {
+            (None, _) => self.try_combine(rhs, f),
+            (Some(probability), ZeroDivisor::Error) => Err(LlDoiceError::DivisionByZero {
+                probability: probability.to_f64().unwrap_or(f64::NAN),
+                span: None,
+            }),
+            (Some(_), ZeroDivisor::DropAndRenormalize) => {
+                let (nonzero, _) =
+                    rhs.given(|k| k != 0)
+                        .map_err(|_| LlDoiceError::DivisionByZero {
+                            probability: 1.0,
+                            span: None,
+                        })?;
+                self.try_combine(&nonzero, f)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ratio;
+    use num::BigRational;
+
+    #[test]
+    fn rounding() {
+        for (a, b, zero, floor, ceil) in [
+            (7, 2, 3, 3, 4),
+            (-7, 2, -3, -4, -3),
+            (7, -2, -3, -4, -3),
+            (-7, -2, 3, 3, 4),
+            (6, 3, 2, 2, 2),
+        ] {
+            assert_eq!(Rounding::TowardZero.div(a, b), Some(zero));
+            assert_eq!(Rounding::Floor.div(a, b), Some(floor));
+            assert_eq!(Rounding::Ceil.div(a, b), Some(ceil));
+        }
+        assert_eq!(Rounding::Floor.div(1, 0), None);
+        assert_eq!(Rounding::TowardZero.div(Sample::MIN, -1), None);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let d6 = PDF::<BigRational, true>::die(6);
+        // Outcomes -1, 0 and 1
+        let divisor = PDF::<BigRational, true>::die(3).offset(-2);
+
+        assert_eq!(
+            d6.checked_div(&divisor, DivisionPolicy::default()),
+            Err(LlDoiceError::DivisionByZero {
+                probability: 1.0 / 3.0,
+                span: None,
+            })
+        );
+
+        let policy = DivisionPolicy {
+            rounding: Rounding::TowardZero,
+            zero_divisor: ZeroDivisor::DropAndRenormalize,
+        };
+        let quotient = d6.checked_div(&divisor, policy).unwrap();
+        assert_eq!(
+            quotient,
+            d6.clone()
+                .scale(-1)
+                .add_pointwise(&d6)
+                .scale_probabilities(ratio(1, 2))
+                .validate()
+                .unwrap()
+        );
+
+        // Dividing by a constant matches the operator, when it does not panic
+        let two = PDF::default().offset(2);
+        assert_eq!(
+            d6.checked_div(&two, DivisionPolicy::default()),
+            Ok(&d6 / &two)
+        );
+        assert_eq!(
+            d6.clone().checked_divide(0, Rounding::Floor),
+            Err(LlDoiceError::DivisionByZero {
+                probability: 1.0,
+                span: None
+            })
+        );
+        let always_zero = PDF::<BigRational, true>::default();
+        assert_eq!(
+            d6.checked_div(&always_zero, policy),
+            Err(LlDoiceError::DivisionByZero {
+                probability: 1.0,
+                span: None
+            })
+        );
+    }
+
+    #[test]
+    fn remainder() {
+        let d6 = PDF::<BigRational, true>::die(6);
+        let divisor = PDF::<BigRational, true>::die(3).offset(-2);
+        assert_eq!(
+            d6.checked_rem(&divisor, ZeroDivisor::Error),
+            Err(LlDoiceError::DivisionByZero {
+                probability: 1.0 / 3.0,
+                span: None
+            })
+        );
+        // x % 1 and x % -1 are both 0
+        assert_eq!(
+            d6.checked_rem(&divisor, ZeroDivisor::DropAndRenormalize),
+            Ok(PDF::default())
+        );
+        let three = PDF::default().offset(3);
+        assert_eq!(d6.checked_rem(&three, ZeroDivisor::Error), Ok(&d6 % &three));
+        assert_eq!(d6.clone().checked_modulo(3), Ok(d6.clone().modulo(3)));
+        assert_eq!(
+            d6.checked_modulo(0),
+            Err(LlDoiceError::DivisionByZero {
+                probability: 1.0,
+                span: None
+            })
+        );
+        let min = PDF::<f64, true>::default().offset(Sample::MIN);
+        assert_eq!(
+            min.checked_modulo(-1),
+            Err(LlDoiceError::OutcomeOverflow { span: None })
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        let d6 = PDF::<f64, true>::die(6);
+        assert_eq!(
+            d6.clone().checked_offset(Sample::MAX),
+            Err(LlDoiceError::OutcomeOverflow { span: None })
+        );
+        assert_eq!(
+            d6.clone().checked_scale(Sample::MAX / 2),
+            Err(LlDoiceError::OutcomeOverflow { span: None })
+        );
+        let max = PDF::<f64, true>::default().offset(Sample::MAX);
+        assert_eq!(
+            d6.checked_add(&max),
+            Err(LlDoiceError::OutcomeOverflow { span: None })
+        );
+        assert_eq!(
+            d6.checked_sub(&max.checked_neg().unwrap()),
+            Err(LlDoiceError::OutcomeOverflow { span: None })
+        );
+        assert_eq!(
+            PDF::<f64, true>::default()
+                .offset(Sample::MIN)
+                .checked_neg(),
+            Err(LlDoiceError::OutcomeOverflow { span: None })
+        );
+        assert_eq!(d6.checked_sub(&d6), Ok(&d6 - &d6));
+        let huge = PDF::default().offset(Sample::MAX / 3);
+        assert_eq!(
+            d6.checked_mul(&huge),
+            Err(LlDoiceError::OutcomeOverflow { span: None })
+        );
+        assert_eq!(d6.clone().checked_scale(2), Ok(d6.clone().scale(2)));
+        assert_eq!(d6.clone().checked_offset(-3), Ok(d6.offset(-3)));
+    }
+}
@@ -0,0 +1,157 @@
+//! Comparison queries, like the probability of beating a DC or winning an opposed roll.
+
+use std::ops::RangeBounds;
+
+use super::{Number, Sample, PDF};
+
+/// How ties are resolved in an opposed roll.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TieRule {
+    /// Ties are kept as a separate result.
+    Tie,
+    /// Ties count as a win for the first roll, like an attacker meeting the AC.
+    Win,
+    /// Ties count as a loss for the first roll, like ties going to the defender in a grapple.
+    Lose,
+}
+
+/// The probabilities of winning, losing and tying an opposed roll, from the perspective of the first roll.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opposed<T> {
+    pub win: T,
+    pub lose: T,
+    pub tie: T,
+}
+
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    /// P(self > other) and P(self = other), computed in a single sweep over both PDFs in ascending order.
+    fn sweep_greater_equal<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> (T, T) {
+        let mut greater = T::zero();
+        let mut equal = T::zero();
+        // P(other < a) for the current outcome a
+        let mut below = T::zero();
+        let mut others = other.data.iter().peekable();
+        for (a, p) in &self.data {
+            let mut at = T::zero();
+            while let Some((b, q)) = others.peek() {
+                if *b < a {
+                    below += *q;
+                    others.next();
+                } else {
+                    if *b == a {
+                        at = (*q).clone();
+                    }
+                    break;
+                }
+            }
+            greater += p.clone() * &below;
+            equal += p.clone() * &at;
+        }
+        (greater, equal)
+    }
+
+    /// P(self > other), for independent rolls.
+    pub fn prob_gt<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> T {
+        self.sweep_greater_equal(other).0
+    }
+
+    /// P(self < other), for independent rolls.
+    pub fn prob_lt<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> T {
+        other.sweep_greater_equal(self).0
+    }
+
+    /// P(self = other), for independent rolls.
+    pub fn prob_eq<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> T {
+        self.sweep_greater_equal(other).1
+    }
+
+    /// P(self in range), like the probability of rolling 10 to 15.
+    pub fn prob_in(&self, range: impl RangeBounds<Sample>) -> T {
+        self.data
+            .range(range)
+            .fold(T::zero(), |acc, (_, v)| acc + v)
+    }
+
+    /// P(self >= k), like the probability of meeting a DC.
+    pub fn prob_ge(&self, k: Sample) -> T {
+        self.prob_in(k..)
+    }
+
+    /// P(self <= k).
+    pub fn prob_le(&self, k: Sample) -> T {
+        self.prob_in(..=k)
+    }
+
+    /// The result of an opposed roll against other, like Athletics against Acrobatics.
+    ///
+    /// The ties are resolved according to the tie rule.
+    pub fn opposed<const OTHER: bool>(
+        &self,
+        other: &PDF<T, OTHER>,
+        tie_rule: TieRule,
+    ) -> Opposed<T> {
+        let (win, tie) = self.sweep_greater_equal(other);
+        let lose = self.prob_lt(other);
+        match tie_rule {
+            TieRule::Tie => Opposed { win, lose, tie },
+            TieRule::Win => Opposed {
+                win: win + &tie,
+                lose,
+                tie: T::zero(),
+            },
+            TieRule::Lose => Opposed {
+                win,
+                lose: lose + &tie,
+                tie: T::zero(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ratio;
+    use num::BigRational;
+
+    #[test]
+    fn against_brute_force() {
+        let a = PDF::ntimes(2, &PDF::<BigRational, true>::die(6)).offset(1);
+        let b = PDF::<BigRational, true>::die(12).offset(2);
+        let (mut gt, mut eq, mut lt) = (ratio(0, 1), ratio(0, 1), ratio(0, 1));
+        for (x, p) in a.data() {
+            for (y, q) in b.data() {
+                let prob = p * q;
+                match x.cmp(y) {
+                    std::cmp::Ordering::Greater => gt += prob,
+                    std::cmp::Ordering::Equal => eq += prob,
+                    std::cmp::Ordering::Less => lt += prob,
+                }
+            }
+        }
+        assert_eq!(a.prob_gt(&b), gt);
+        assert_eq!(a.prob_eq(&b), eq);
+        assert_eq!(a.prob_lt(&b), lt);
+        assert_eq!(
+            a.opposed(&b, TieRule::Tie),
+            Opposed {
+                win: gt.clone(),
+                lose: lt.clone(),
+                tie: eq.clone()
+            }
+        );
+        assert_eq!(a.opposed(&b, TieRule::Win).win, gt + &eq);
+        assert_eq!(a.opposed(&b, TieRule::Lose).lose, lt + &eq);
+    }
+
+    #[test]
+    fn thresholds() {
+        let d20 = PDF::<BigRational, true>::die(20);
+        assert_eq!(d20.prob_ge(11), ratio(1, 2));
+        assert_eq!(d20.prob_le(5), ratio(1, 4));
+        assert_eq!(d20.prob_in(10..15), ratio(1, 4));
+        assert_eq!(d20.prob_ge(21), ratio(0, 1));
+        assert_eq!(d20.prob_gt(&d20), ratio(190, 400));
+        assert_eq!(d20.prob_eq(&d20), ratio(1, 20));
+    }
+}
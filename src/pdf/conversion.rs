@@ -0,0 +1,88 @@
+//! Conversions between PDFs with different probability types.
+
+use std::collections::BTreeMap;
+
+use num::{BigInt, BigRational};
+
+use crate::numerics::{Fpp, ToFpp};
+use crate::LlDoiceError;
+
+use super::{Number, Sample, PDF};
+
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    /// Convert all probabilities to f64.
+    ///
+    /// Soundness is kept, as the rounding errors are far below the validation tolerance.
+    pub fn to_f64(&self) -> PDF<f64, SOUND> {
+        PDF {
+            data: self
+                .data
+                .iter()
+                .map(|(k, v)| (*k, v.to_f64().expect("Number must be convertible to f64.")))
+                .collect(),
+        }
+    }
+
+    /// Convert all probabilities to Fpp.
+    ///
+    /// Rounding can push the total of a sound PDF just above what Fpp can hold, like the two halves of a d2.
+    /// Sound PDFs are then scaled down to fit, so the result can still be summed without overflowing.
+    ///
+    /// Fails when any of the probabilities is outside of [0, 1].
+    pub fn to_fpp(&self) -> Result<PDF<Fpp, SOUND>, LlDoiceError> {
+        let mut data: BTreeMap<Sample, Fpp> = self
+            .data
+            .iter()
+            .map(|(k, v)| Ok((*k, v.clone().to_fpp()?)))
+            .collect::<Result<_, LlDoiceError>>()?;
+
+        let max = Fpp::MAX.inner() as u128;
+        let total: u128 = data.values().map(|v| v.inner() as u128).sum();
+        if SOUND && total > max {
+            // Rounding down keeps the scaled total at most max
+            for v in data.values_mut() {
+                *v = Fpp::from_inner((v.inner() as u128 * max / total) as usize);
+            }
+        }
+        Ok(PDF { data })
+    }
+}
+
+impl<const SOUND: bool> PDF<f64, SOUND> {
+    /// Convert all probabilities to the exact rational value of the float.
+    ///
+    /// The floats generally do not sum to exactly 1, so the result can not be assumed to be sound.
+    ///
+    /// # Panics
+    /// Panics when any probability is NaN or infinite.
+    pub fn to_rational(&self) -> PDF<BigRational, false> {
+        PDF {
+            data: self
+                .data
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        *k,
+                        BigRational::from_float(*v).expect("Probability must be finite."),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<const SOUND: bool> PDF<Fpp, SOUND> {
+    /// Convert all probabilities to the exact rational value of the fixed point number.
+    ///
+    /// Rounding means the probabilities generally do not sum to exactly 1, so the result can not be assumed to be sound.
+    pub fn to_rational(&self) -> PDF<BigRational, false> {
+        let max = BigInt::from(Fpp::MAX.inner());
+        PDF {
+            data: self
+                .data
+                .iter()
+                .map(|(k, v)| (*k, BigRational::new(v.inner().into(), max.clone())))
+                .collect(),
+        }
+    }
+}
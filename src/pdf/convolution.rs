@@ -0,0 +1,328 @@
+//! Convolution algorithms used when adding PDFs.
+//!
+//! The naive algorithm works for any `Number`, but is O(n·m).
+//! Probability types can opt into faster algorithms by implementing `Convolve::convolve_dense`,
+//! which is used whenever both operands are dense over a contiguous outcome range.
+//! Floats use the FFT, while integers and rationals use an exact NTT.
+
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use num::{BigInt, BigRational, BigUint, FromPrimitive, Integer, One, Signed, ToPrimitive};
+
+use crate::numerics::{fft, ntt, Fpp, LogProb};
+
+use super::{Number, Sample};
+
+/// Minimum number of outcomes both operands need before the FFT is used.
+/// Below this, the naive algorithm is faster anyway.
+const FFT_THRESHOLD: usize = 32;
+/// Minimum number of outcomes both operands need before the NTT is used.
+const NTT_THRESHOLD: usize = 32;
+
+/// Allows a probability type to provide a faster convolution than the naive O(n·m) algorithm.
+///
+/// The naive algorithm is always available as a fallback,
+/// so opting out is as simple as `impl Convolve for MyType {}`.
+pub trait Convolve: Sized {
+    /// Convolve two dense probability vectors, where entry i of the result corresponds to the sum of the
+    /// outcomes at index j in lhs and index i - j in rhs.
+    ///
+    /// Returns None when no fast algorithm applies, in which case the naive algorithm is used.
+    fn convolve_dense(_lhs: &[Self], _rhs: &[Self]) -> Option<Vec<Self>> {
+        None
+    }
+}
+
+/// How far above the FFT noise floor a result must be to be trusted.
+/// Entries below this are recomputed directly, so their relative error stays below about `1e-9`.
+const FFT_TAIL_MARGIN: f64 = 1e9;
+
+/// Entry i of the convolution, computed directly.
+fn convolve_entry(lhs: &[f64], rhs: &[f64], i: usize) -> f64 {
+    let start = i.saturating_sub(rhs.len() - 1);
+    let end = i.min(lhs.len() - 1);
+    (start..=end).map(|j| lhs[j] * rhs[i - j]).sum()
+}
+
+/// Float probabilities are convolved using the FFT.
+///
+/// The error of the FFT is absolute, proportional to the norms of both operands,
+/// which would drown the tails of large sums like 40d20 in noise.
+/// So entries that are not well above that noise are recomputed directly,
+/// keeping the relative error low across the whole result.
+/// Dense results with a large central mass still take O(n log n) plus the size of the tails.
+impl Convolve for f64 {
+    fn convolve_dense(lhs: &[f64], rhs: &[f64]) -> Option<Vec<f64>> {
+        if lhs.len().min(rhs.len()) < FFT_THRESHOLD {
+            return None;
+        }
+        let mut result = fft::convolve_real(lhs, rhs);
+
+        let norm = |s: &[f64]| s.iter().map(|v| v * v).sum::<f64>().sqrt();
+        let noise = norm(lhs) * norm(rhs) * f64::EPSILON * (result.len() as f64).log2();
+        let trusted = noise * FFT_TAIL_MARGIN;
+        for (i, v) in result.iter_mut().enumerate() {
+            if v.abs() < trusted {
+                *v = convolve_entry(lhs, rhs, i);
+            }
+        }
+        Some(result)
+    }
+}
+
+impl Convolve for f32 {
+    fn convolve_dense(lhs: &[f32], rhs: &[f32]) -> Option<Vec<f32>> {
+        let lhs: Vec<f64> = lhs.iter().map(|&v| v as f64).collect();
+        let rhs: Vec<f64> = rhs.iter().map(|&v| v as f64).collect();
+        f64::convolve_dense(&lhs, &rhs).map(|res| res.into_iter().map(|v| v as f32).collect())
+    }
+}
+
+/// Integer probabilities (usually counts of ways to roll an outcome) are convolved exactly using the NTT.
+///
+/// Falls back to the naive algorithm for negative entries, or when the result might not fit in a u128.
+macro_rules! impl_convolve_int {
+    ($($t:ty),*) => {
+        $(
+            impl Convolve for $t {
+                fn convolve_dense(lhs: &[$t], rhs: &[$t]) -> Option<Vec<$t>> {
+                    if lhs.len().min(rhs.len()) < NTT_THRESHOLD {
+                        return None;
+                    }
+                    let lhs: Option<Vec<u64>> = lhs.iter().map(|v| v.to_u64()).collect();
+                    let rhs: Option<Vec<u64>> = rhs.iter().map(|v| v.to_u64()).collect();
+                    ntt::convolve_u64(&lhs?, &rhs?)?
+                        .into_iter()
+                        .map(<$t>::from_u128)
+                        .collect()
+                }
+            }
+        )*
+    };
+}
+
+impl_convolve_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl Convolve for BigUint {
+    fn convolve_dense(lhs: &[BigUint], rhs: &[BigUint]) -> Option<Vec<BigUint>> {
+        if lhs.len().min(rhs.len()) < NTT_THRESHOLD {
+            return None;
+        }
+        ntt::convolve_biguint(lhs, rhs)
+    }
+}
+
+impl Convolve for BigInt {
+    fn convolve_dense(lhs: &[BigInt], rhs: &[BigInt]) -> Option<Vec<BigInt>> {
+        let lhs: Option<Vec<BigUint>> = lhs.iter().map(BigInt::to_biguint).collect();
+        let rhs: Option<Vec<BigUint>> = rhs.iter().map(BigInt::to_biguint).collect();
+        BigUint::convolve_dense(&lhs?, &rhs?).map(|res| res.into_iter().map(BigInt::from).collect())
+    }
+}
+
+/// Rationals are brought to a common denominator, after which the numerators are convolved exactly.
+impl Convolve for BigRational {
+    fn convolve_dense(lhs: &[BigRational], rhs: &[BigRational]) -> Option<Vec<BigRational>> {
+        if lhs.len().min(rhs.len()) < NTT_THRESHOLD {
+            return None;
+        }
+
+        // Returns the common denominator, and the numerators with respect to it
+        let common = |s: &[BigRational]| -> Option<(BigInt, Vec<BigUint>)> {
+            if s.iter().any(Signed::is_negative) {
+                return None;
+            }
+            let denom = s.iter().fold(BigInt::one(), |acc, v| acc.lcm(v.denom()));
+            let numers = s
+                .iter()
+                .map(|v| v.numer() * (&denom / v.denom()))
+                .map(|v| v.to_biguint().expect("Entries are non-negative."))
+                .collect();
+            Some((denom, numers))
+        };
+
+        let (lhs_denom, lhs_numers) = common(lhs)?;
+        let (rhs_denom, rhs_numers) = common(rhs)?;
+        let denom = lhs_denom * rhs_denom;
+
+        Some(
+            ntt::convolve_biguint(&lhs_numers, &rhs_numers)?
+                .into_iter()
+                .map(|numer| BigRational::new(numer.into(), denom.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// The FFT is not precise enough for Fpp, so the naive algorithm is used.
+impl Convolve for Fpp {}
+
+/// The FFT would underflow the tiny probabilities LogProb is meant for, so the naive algorithm is used.
+impl Convolve for LogProb {}
+
+/// Returns the first outcome and the probabilities of a PDF, if its outcomes form a contiguous range.
+fn as_dense<T: Clone>(data: &BTreeMap<Sample, T>) -> Option<(Sample, Vec<T>)> {
+    let (first, _) = data.first_key_value()?;
+    let (last, _) = data.last_key_value()?;
+    // The width of PDFs with outcomes near both ends of Sample does not fit in a Sample
+    let width = usize::try_from(last.checked_sub(*first)?).ok()?;
+    if width.checked_add(1)? != data.len() {
+        return None;
+    }
+    Some((*first, data.values().cloned().collect()))
+}
+
+/// The outcomes of the sum of two dense PDFs, or None when the last one does not fit in a Sample.
+fn sum_outcomes(
+    lhs_first: Sample,
+    lhs_len: usize,
+    rhs_first: Sample,
+    rhs_len: usize,
+) -> Option<RangeInclusive<Sample>> {
+    let first = lhs_first.checked_add(rhs_first)?;
+    let last = first.checked_add(Sample::try_from(lhs_len + rhs_len - 2).ok()?)?;
+    Some(first..=last)
+}
+
+/// The naive O(n·m) convolution, which works for any outcomes and any `Number`.
+pub(crate) fn naive<T: Number>(
+    lhs: &BTreeMap<Sample, T>,
+    rhs: &BTreeMap<Sample, T>,
+) -> BTreeMap<Sample, T> {
+    let mut data = BTreeMap::new();
+    for (outcome, prob) in lhs.iter() {
+        for (k, v) in rhs.iter() {
+            data.entry(outcome + k)
+                .and_modify(|e| *e += prob.clone() * v)
+                .or_insert_with(|| prob.clone() * v);
+        }
+    }
+    data
+}
+
+/// Convolve two PDFs, using a fast algorithm when both are dense and the probability type supports it.
+pub(crate) fn convolve<T: Number>(
+    lhs: &BTreeMap<Sample, T>,
+    rhs: &BTreeMap<Sample, T>,
+) -> BTreeMap<Sample, T> {
+    if let (Some((lhs_first, lhs_dense)), Some((rhs_first, rhs_dense))) =
+        (as_dense(lhs), as_dense(rhs))
+    {
+        if let Some(outcomes) = sum_outcomes(lhs_first, lhs_dense.len(), rhs_first, rhs_dense.len())
+        {
+            if let Some(result) = T::convolve_dense(&lhs_dense, &rhs_dense) {
+                return outcomes.zip(result).collect();
+            }
+        }
+    }
+    naive(lhs, rhs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn die(sides: Sample) -> BTreeMap<Sample, f64> {
+        (1..=sides).map(|k| (k, 1.0 / sides as f64)).collect()
+    }
+
+    #[test]
+    fn fft_matches_naive() {
+        let d100 = die(100);
+        let mut naive_sum = d100.clone();
+        let mut fast_sum = d100.clone();
+        for _ in 0..3 {
+            naive_sum = naive(&naive_sum, &d100);
+            fast_sum = convolve(&fast_sum, &d100);
+        }
+
+        assert_eq!(
+            naive_sum.keys().collect::<Vec<_>>(),
+            fast_sum.keys().collect::<Vec<_>>()
+        );
+        for (n, f) in naive_sum.values().zip(fast_sum.values()) {
+            assert!((n - f).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn fft_keeps_tails() {
+        let relative_error = |a: f64, b: f64| ((a - b) / b).abs();
+
+        // 20d20 is dense enough for the FFT, and 40d20 has tails around 1e-52
+        let d20 = die(20);
+        let mut twenty = d20.clone();
+        for _ in 1..20 {
+            twenty = naive(&twenty, &d20);
+        }
+        let fast_sum = convolve(&twenty, &twenty);
+        let naive_sum = naive(&twenty, &twenty);
+        assert_eq!(
+            fast_sum.keys().copied().collect::<Vec<_>>(),
+            (40..=800).collect::<Vec<_>>()
+        );
+        for (n, f) in naive_sum.values().zip(fast_sum.values()) {
+            assert!(relative_error(*f, *n) < 1e-9, "{f} != {n}");
+        }
+
+        let d6 = die(6);
+        let mut fifty = d6.clone();
+        for _ in 1..50 {
+            fifty = naive(&fifty, &d6);
+        }
+        let hundred = convolve(&fifty, &fifty);
+        assert!(relative_error(hundred[&100], 6f64.powi(-100)) < 1e-9);
+        assert!(relative_error(hundred[&600], 6f64.powi(-100)) < 1e-9);
+    }
+
+    #[test]
+    fn ntt_is_exact() {
+        let d6: BTreeMap<Sample, BigRational> = (1..=6)
+            .map(|k| (k, BigRational::new(1.into(), 6.into())))
+            .collect();
+        let mut naive_sum = d6.clone();
+        for _ in 0..9 {
+            naive_sum = naive(&naive_sum, &d6);
+        }
+
+        // 10d6 + 10d6 is dense enough to use the NTT
+        assert_eq!(
+            convolve(&naive_sum, &naive_sum),
+            naive(&naive_sum, &naive_sum)
+        );
+
+        let counts: BTreeMap<Sample, u64> = (0..40).map(|k| (k, k as u64 * 1_000_003)).collect();
+        assert_eq!(convolve(&counts, &counts), naive(&counts, &counts));
+    }
+
+    #[test]
+    fn extreme_outcomes() {
+        // The width of this PDF does not fit in a Sample
+        let extremes = BTreeMap::from([(Sample::MIN, 0.5), (Sample::MAX, 0.5)]);
+        let zero = BTreeMap::from([(0, 1.0)]);
+        assert_eq!(convolve(&extremes, &zero), extremes);
+
+        // Dense enough for the FFT, with a sum that ends at Sample::MAX
+        let top: BTreeMap<Sample, f64> = (Sample::MAX - 39..=Sample::MAX)
+            .map(|k| (k, 1.0 / 40.0))
+            .collect();
+        let below: BTreeMap<Sample, f64> = (-39..=0).map(|k| (k, 1.0 / 40.0)).collect();
+        let fast_sum = convolve(&top, &below);
+        let naive_sum = naive(&top, &below);
+        assert_eq!(fast_sum.keys().last(), Some(&Sample::MAX));
+        assert_eq!(
+            naive_sum.keys().collect::<Vec<_>>(),
+            fast_sum.keys().collect::<Vec<_>>()
+        );
+        for (n, f) in naive_sum.values().zip(fast_sum.values()) {
+            assert!((n - f).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn sparse_uses_naive() {
+        let sparse: BTreeMap<Sample, f64> = (0..64).map(|k| (k * 2, 1.0 / 64.0)).collect();
+        let result = convolve(&sparse, &sparse);
+        assert_eq!(result, naive(&sparse, &sparse));
+    }
+}
@@ -0,0 +1,269 @@
+//! A PDF storing integer counts with a single shared denominator.
+
+use std::{
+    collections::BTreeMap,
+    ops::{Add, RangeInclusive},
+};
+
+use num::{BigInt, BigRational};
+
+use super::{convolution, Number, Sample, PDF};
+
+/// A discrete probability distribution, where every probability is count / total.
+///
+/// For uniform dice, every probability is `ways / sides^n`,
+/// so storing the number of ways to roll each outcome is both exact and much cheaper than storing rationals.
+/// The counts are convolved exactly using the NTT.
+///
+/// The counts always sum to the total, so a CountPdf is always sound.
+/// Fixed size counts like u64 can overflow for large rolls, in which case `BigUint` should be used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountPdf<C> {
+    counts: BTreeMap<Sample, C>,
+    total: C,
+}
+
+impl<C: Number> CountPdf<C> {
+    /// A uniform distribution over all outcomes in the range.
+    ///
+    /// # Panics
+    /// Panics when the range is empty, as the total would be 0.
+    pub fn uniform(range: RangeInclusive<Sample>) -> Self {
+        assert!(!range.is_empty(), "Cannot be uniform over an empty range.");
+        let counts: BTreeMap<Sample, C> = range.map(|k| (k, C::one())).collect();
+        let total = C::from_usize(counts.len()).expect("Number of outcomes must fit in C.");
+        CountPdf { counts, total }
+    }
+
+    /// A fair die with outcomes 1 to sides.
+    ///
+    /// # Panics
+    /// Panics when the die has no sides.
+    pub fn die(sides: Sample) -> Self {
+        Self::uniform(1..=sides)
+    }
+
+    /// The number of ways to roll each outcome.
+    pub fn counts(&self) -> &BTreeMap<Sample, C> {
+        &self.counts
+    }
+
+    /// The total number of ways to roll, which is the shared denominator of all probabilities.
+    pub fn total(&self) -> &C {
+        &self.total
+    }
+
+    /// Apply an offset to all outcomes.
+    pub fn offset(self, offset: Sample) -> Self {
+        CountPdf {
+            counts: self
+                .counts
+                .into_iter()
+                .map(|(k, v)| (k + offset, v))
+                .collect(),
+            total: self.total,
+        }
+    }
+
+    /// Apply a scale to all outcomes.
+    pub fn scale(self, scale: Sample) -> Self {
+        let mut counts = BTreeMap::new();
+        // Scaling by 0 merges outcomes
+        for (k, v) in self.counts {
+            counts
+                .entry(k * scale)
+                .and_modify(|e| *e += &v)
+                .or_insert(v);
+        }
+        CountPdf {
+            counts,
+            total: self.total,
+        }
+    }
+
+    /// Sum n independent copies, using exponentiation by squaring.
+    /// The sum of zero copies is always 0.
+    pub fn repeat_sum(self, mut n: usize) -> Self {
+        let mut result: Option<Self> = None;
+        let mut base = self;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = Some(match result {
+                    Some(result) => &result + &base,
+                    None => base.clone(),
+                });
+            }
+            n >>= 1;
+            if n > 0 {
+                base = &base + &base;
+            }
+        }
+        result.unwrap_or_else(|| CountPdf {
+            counts: [(0, C::one())].into(),
+            total: C::one(),
+        })
+    }
+
+    /// Roll n of the given die and sum the results, like NdX.
+    pub fn ntimes(n: usize, die: &Self) -> Self {
+        die.clone().repeat_sum(n)
+    }
+
+    /// Apply n levels of advantage: roll n + 1 times and keep the highest.
+    ///
+    /// Works like `PDF::with_advantage`, using ways(X<=x)^(n+1) - ways(X<x)^(n+1) out of total^(n+1).
+    pub fn with_advantage(&mut self, n: usize) {
+        let mut below = C::zero();
+        for v in self.counts.values_mut() {
+            let at_most = below.clone() + &*v;
+            *v = num::pow(at_most.clone(), n + 1) - &num::pow(below, n + 1);
+            below = at_most;
+        }
+        self.total = num::pow(self.total.clone(), n + 1);
+    }
+
+    /// Apply n levels of disadvantage: roll n + 1 times and keep the lowest.
+    pub fn with_disadvantage(&mut self, n: usize) {
+        let mut above = C::zero();
+        for v in self.counts.values_mut().rev() {
+            let at_least = above.clone() + &*v;
+            *v = num::pow(at_least.clone(), n + 1) - &num::pow(above, n + 1);
+            above = at_least;
+        }
+        self.total = num::pow(self.total.clone(), n + 1);
+    }
+
+    /// Cumulative counts, P(X<=x) * total.
+    pub fn cumulative(&self) -> BTreeMap<Sample, C> {
+        self.counts
+            .iter()
+            .scan(C::zero(), |state, (k, v)| {
+                *state += v;
+                Some((*k, state.clone()))
+            })
+            .collect()
+    }
+
+    /// Exclusive cumulative counts, P(X<x) * total.
+    pub fn cumulative_exclusive(&self) -> BTreeMap<Sample, C> {
+        self.counts
+            .iter()
+            .scan(C::zero(), |state, (k, v)| {
+                let val = state.clone();
+                *state += v;
+                Some((*k, val))
+            })
+            .collect()
+    }
+
+    /// Reverse cumulative counts, P(X>=x) * total.
+    pub fn rev_cumulative(&self) -> BTreeMap<Sample, C> {
+        self.counts
+            .iter()
+            .rev()
+            .scan(C::zero(), |state, (k, v)| {
+                *state += v;
+                Some((*k, state.clone()))
+            })
+            .collect()
+    }
+
+    /// Exclusive reverse cumulative counts, P(X>x) * total.
+    pub fn rev_cumulative_exclusive(&self) -> BTreeMap<Sample, C> {
+        self.counts
+            .iter()
+            .rev()
+            .scan(C::zero(), |state, (k, v)| {
+                let val = state.clone();
+                *state += v;
+                Some((*k, val))
+            })
+            .collect()
+    }
+}
+
+impl<C: Number + Into<BigInt>> CountPdf<C> {
+    /// The exact probability of an outcome.
+    pub fn p(&self, outcome: Sample) -> BigRational {
+        let count = self.counts.get(&outcome).cloned().unwrap_or_else(C::zero);
+        BigRational::new(count.into(), self.total.clone().into())
+    }
+}
+
+impl<C: Number + Into<BigInt>> From<CountPdf<C>> for PDF<BigRational, true> {
+    /// Losslessly convert the counts into exact rational probabilities.
+    fn from(value: CountPdf<C>) -> Self {
+        let total: BigInt = value.total.into();
+        PDF {
+            data: value
+                .counts
+                .into_iter()
+                .map(|(k, v)| (k, BigRational::new(v.into(), total.clone())))
+                .collect(),
+        }
+    }
+}
+
+impl<C: Number> Add<&CountPdf<C>> for &CountPdf<C> {
+    type Output = CountPdf<C>;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, rhs: &CountPdf<C>) -> Self::Output {
+        CountPdf {
+            counts: convolution::convolve(&self.counts, &rhs.counts),
+            total: self.total.clone() * &rhs.total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigUint;
+
+    #[test]
+    fn matches_rational_pdf() {
+        let d6 = CountPdf::<u64>::die(6);
+        let r_d6 = PDF::<BigRational, true>::die(6);
+
+        let sum = CountPdf::ntimes(10, &d6).offset(2).scale(3);
+        let r_sum = PDF::ntimes(10, &r_d6).offset(2).scale(3);
+        assert_eq!(PDF::from(sum), r_sum);
+
+        let mut adv = CountPdf::<u64>::die(20);
+        adv.with_advantage(2);
+        let mut r_adv = PDF::<BigRational, true>::die(20);
+        r_adv.with_advantage(2);
+        assert_eq!(PDF::from(adv), r_adv);
+
+        let mut dis = CountPdf::<u64>::die(20);
+        dis.with_disadvantage(1);
+        let mut r_dis = PDF::<BigRational, true>::die(20);
+        r_dis.with_disadvantage(1);
+        assert_eq!(PDF::from(dis.clone()), r_dis);
+        assert_eq!(dis.cumulative()[&20], *dis.total());
+        assert_eq!(dis.rev_cumulative_exclusive()[&20], 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "empty range")]
+    fn no_sides() {
+        CountPdf::<u64>::die(0);
+    }
+
+    #[test]
+    fn big_counts() {
+        // 6^100 does not fit in any primitive
+        let sum = CountPdf::ntimes(100, &CountPdf::<BigUint>::die(6));
+        assert_eq!(*sum.total(), BigUint::from(6u32).pow(100));
+        assert_eq!(sum.counts()[&100], BigUint::from(1u32));
+        assert_eq!(sum.counts()[&101], BigUint::from(100u32));
+        assert_eq!(
+            sum.p(600),
+            BigRational::new(1.into(), BigInt::from(6).pow(100))
+        );
+
+        let pdf = PDF::from(sum);
+        assert!(pdf.assert_unsoundness().validate().is_ok());
+    }
+}
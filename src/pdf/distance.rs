@@ -0,0 +1,160 @@
+//! Information-theoretic measures and distances between PDFs.
+//!
+//! All measures are computed in f64 over the merged supports of both PDFs,
+//! with the probabilities divided by their totals, like the summary statistics.
+//! Information measures are in bits.
+
+use std::collections::BTreeMap;
+
+use super::{Number, Sample, PDF};
+
+/// p * log2(p / q), with the limits for p = 0 and q = 0 taken explicitly.
+fn relative_information(p: f64, q: f64) -> f64 {
+    if p == 0.0 {
+        0.0
+    } else if q == 0.0 {
+        f64::INFINITY
+    } else {
+        p * (p / q).log2()
+    }
+}
+
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    /// The normalized probabilities of both PDFs, for every outcome in either support.
+    /// Outcomes missing from one of them have a probability of 0 there.
+    fn merged_pmfs<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> Vec<(f64, f64)> {
+        let mut merged: BTreeMap<Sample, (f64, f64)> = BTreeMap::new();
+        for (k, p) in self.weights() {
+            merged.entry(k).or_default().0 = p;
+        }
+        for (k, q) in other.weights() {
+            merged.entry(k).or_default().1 = q;
+        }
+        merged.into_values().collect()
+    }
+
+    /// The normalized cumulative probabilities of both PDFs, for every outcome in either support.
+    fn merged_cdfs<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> Vec<(Sample, f64, f64)> {
+        let normalized_cdf = |cumulative: PDF<T, false>, total: f64| {
+            move |x: Sample| {
+                cumulative
+                    .get_value_below(x)
+                    .to_f64()
+                    .expect("Number must be convertible to f64.")
+                    / total
+            }
+        };
+        let f = normalized_cdf(self.cumulative(), Self::f64_total(self.data.values()));
+        let g = normalized_cdf(other.cumulative(), Self::f64_total(other.data.values()));
+
+        let mut support: Vec<Sample> = self.data.keys().chain(other.data.keys()).copied().collect();
+        support.sort_unstable();
+        support.dedup();
+        support.into_iter().map(|x| (x, f(x), g(x))).collect()
+    }
+
+    /// The Shannon entropy, -Σ p log2(p).
+    pub fn entropy(&self) -> f64 {
+        -self
+            .weights()
+            .into_iter()
+            .map(|(_, p)| p * p.log2())
+            .sum::<f64>()
+    }
+
+    /// The Kullback-Leibler divergence D(self || other), Σ p log2(p / q).
+    ///
+    /// This is infinite when self has outcomes that are impossible for other,
+    /// which includes disjoint supports.
+    pub fn kl_divergence<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> f64 {
+        self.merged_pmfs(other)
+            .into_iter()
+            .map(|(p, q)| relative_information(p, q))
+            .sum()
+    }
+
+    /// The Jensen-Shannon divergence, the mean KL divergence of both PDFs from their average.
+    ///
+    /// Unlike the KL divergence this is symmetric and always finite,
+    /// ranging from 0 for identical PDFs to 1 for disjoint supports.
+    pub fn js_divergence<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> f64 {
+        self.merged_pmfs(other)
+            .into_iter()
+            .map(|(p, q)| {
+                let m = (p + q) / 2.0;
+                (relative_information(p, m) + relative_information(q, m)) / 2.0
+            })
+            .sum()
+    }
+
+    /// The total variation distance, the largest difference in probability either PDF can assign to an event.
+    /// Ranges from 0 to 1, which is reached for disjoint supports.
+    pub fn total_variation<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> f64 {
+        self.merged_pmfs(other)
+            .into_iter()
+            .map(|(p, q)| (p - q).abs())
+            .sum::<f64>()
+            / 2.0
+    }
+
+    /// The Kolmogorov-Smirnov distance, the largest difference between the cumulative distributions.
+    pub fn ks_distance<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> f64 {
+        self.merged_cdfs(other)
+            .into_iter()
+            .map(|(_, f, g)| (f - g).abs())
+            .fold(0.0, f64::max)
+    }
+
+    /// The Wasserstein-1 (earth mover's) distance, the area between the cumulative distributions.
+    ///
+    /// This is how far probability has to be moved on average to turn one PDF into the other,
+    /// so unlike the other measures it keeps growing as disjoint supports move further apart.
+    pub fn wasserstein<const OTHER: bool>(&self, other: &PDF<T, OTHER>) -> f64 {
+        self.merged_cdfs(other)
+            .windows(2)
+            .map(|w| {
+                let (x, f, g) = w[0];
+                (f - g).abs() * (w[1].0 - x) as f64
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_close;
+
+    #[test]
+    fn information() {
+        let d6 = PDF::<f64, true>::die(6);
+        let d8 = PDF::<f64, true>::die(8);
+        assert_close(d8.entropy(), 3.0);
+        assert_close(d6.entropy(), 6f64.log2());
+
+        assert_close(d6.kl_divergence(&d6), 0.0);
+        assert_close(d6.kl_divergence(&d8), (8.0f64 / 6.0).log2());
+        assert_eq!(d8.kl_divergence(&d6), f64::INFINITY);
+
+        let shifted = d6.clone().offset(10);
+        assert_close(d6.js_divergence(&d6), 0.0);
+        assert_close(d6.js_divergence(&shifted), 1.0);
+        assert_close(d6.js_divergence(&d8), d8.js_divergence(&d6));
+    }
+
+    #[test]
+    fn distances() {
+        let d6 = PDF::<f64, true>::die(6);
+        let d8 = PDF::<f64, true>::die(8);
+        assert_close(d6.total_variation(&d8), 0.25);
+        assert_close(d6.ks_distance(&d8), 0.25);
+        // d8 is at least as high as d6 everywhere, so this is the difference in means
+        assert_close(d6.wasserstein(&d8), 1.0);
+
+        let shifted = d6.clone().offset(10);
+        assert_close(d6.total_variation(&shifted), 1.0);
+        assert_close(d6.ks_distance(&shifted), 1.0);
+        assert_close(d6.wasserstein(&shifted), 10.0);
+        assert_close(d6.wasserstein(&shifted.scale_probabilities(0.5)), 10.0);
+    }
+}
@@ -0,0 +1,362 @@
+//! Common dice mechanics, like exploding dice, rerolls and success counting.
+
+use std::{collections::BTreeMap, ops::RangeBounds};
+
+use crate::LlDoiceError;
+
+use super::{
+    convolution,
+    order::{binomial_pmfs, conditional},
+    MinMaxPDF, Number, Sample, PDF,
+};
+
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    /// Shared implementation of the exploding dice variants.
+    ///
+    /// Every roll in a chain contributes `value` of its outcome, and every extra roll has `penalty` subtracted from it.
+    /// Returns the result, along with the probability of the chains that were cut off at max_depth.
+    fn explode_with(
+        &self,
+        trigger: impl Fn(Sample) -> bool,
+        max_depth: usize,
+        penalty: Sample,
+        value: impl Fn(Sample) -> Sample,
+    ) -> (Self, T) {
+        let mut exploding: BTreeMap<Sample, T> = BTreeMap::new();
+        let mut stopping: BTreeMap<Sample, T> = BTreeMap::new();
+        let mut result: BTreeMap<Sample, T> = BTreeMap::new();
+        let mut trigger_prob = T::zero();
+        for (k, v) in self.data.iter() {
+            let add = |data: &mut BTreeMap<Sample, T>| {
+                data.entry(value(*k))
+                    .and_modify(|e| *e += v)
+                    .or_insert_with(|| v.clone());
+            };
+            if trigger(*k) {
+                trigger_prob += v;
+                add(&mut exploding);
+            } else {
+                add(&mut stopping);
+            }
+            // At max depth, the triggering outcomes simply stay as they are
+            add(&mut result);
+        }
+
+        for _ in 0..max_depth {
+            let next = result.into_iter().map(|(k, v)| (k - penalty, v)).collect();
+            let exploded = PDF::<T, false> {
+                data: convolution::convolve(&exploding, &next),
+            };
+            result = exploded
+                .add_pointwise(&PDF {
+                    data: stopping.clone(),
+                })
+                .data;
+        }
+
+        (PDF { data: result }, num::pow(trigger_prob, max_depth + 1))
+    }
+
+    /// Exploding dice, like `d6!`: whenever the trigger matches, the die is rolled again and added.
+    ///
+    /// At most max_depth extra rolls are made.
+    /// Chains that would explode beyond that keep their current total, so no probability is lost.
+    /// The probability of such a cut-off chain is returned alongside the result,
+    /// so callers can decide whether max_depth was deep enough.
+    ///
+    /// This is the total of the chain, which is the same for compounding dice like `d6!!`.
+    /// When the extra rolls count as separate dice in a pool, use `explode_count_successes` instead.
+    pub fn explode(&self, trigger: impl Fn(Sample) -> bool, max_depth: usize) -> (Self, T) {
+        self.explode_with(trigger, max_depth, 0, |k| k)
+    }
+
+    /// Distribution of the number of successes in a pool of n exploding dice,
+    /// where every extra roll is a separate die that can be a success, like Shadowrun's rule of six.
+    ///
+    /// Compounding dice instead add the extra rolls to the same die, which counts as a single success at most.
+    /// Those are counted with `explode` followed by `count_successes`.
+    /// Also returns the probability that any of the n chains was cut off at max_depth.
+    pub fn explode_count_successes(
+        &self,
+        n: usize,
+        trigger: impl Fn(Sample) -> bool,
+        success: impl Fn(Sample) -> bool,
+        max_depth: usize,
+    ) -> (Self, T) {
+        let (chain, truncated) =
+            self.explode_with(trigger, max_depth, 0, |k| Sample::from(success(k)));
+        let complete = num::pow(T::one() - &truncated, n);
+        (chain.repeat_sum(n), T::one() - &complete)
+    }
+
+    /// Penetrating exploding dice, like `d6!p`: every extra roll has 1 subtracted from it.
+    pub fn explode_penetrating(
+        &self,
+        trigger: impl Fn(Sample) -> bool,
+        max_depth: usize,
+    ) -> (Self, T) {
+        self.explode_with(trigger, max_depth, 1, |k| k)
+    }
+
+    /// Splits the PDF into the outcomes that match the predicate, and the ones that don't.
+    fn split(&self, pred: impl Fn(Sample) -> bool) -> (PDF<T, false>, PDF<T, false>) {
+        let (matching, other) = self
+            .data
+            .iter()
+            .map(|(k, v)| (*k, v.clone()))
+            .partition(|(k, _)| pred(*k));
+        (PDF { data: matching }, PDF { data: other })
+    }
+
+    /// The distribution given that the outcome matches the predicate, like "d20 given it's not a natural 1".
+    ///
+    /// Returns the renormalised distribution, along with the probability of the condition.
+    /// Returns an error when the condition is impossible.
+    pub fn given(&self, pred: impl Fn(Sample) -> bool) -> Result<(PDF<T, true>, T), LlDoiceError> {
+        let (mut matching, _) = self.split(pred);
+        matching.trim_zeroes();
+        let (given, matching_total) = matching.normalize_with_total()?;
+        Ok((
+            given,
+            conditional(&matching_total, &Self::total(&self.data)),
+        ))
+    }
+
+    /// The distribution given that the outcome lies in the range, like "damage given that it is at least 10".
+    pub fn given_range(
+        &self,
+        range: impl RangeBounds<Sample>,
+    ) -> Result<(PDF<T, true>, T), LlDoiceError> {
+        self.given(|x| range.contains(&x))
+    }
+
+    /// Reroll outcomes matching the predicate once, and keep the new roll, like Great Weapon Fighting.
+    pub fn reroll_once(&self, pred: impl Fn(Sample) -> bool) -> Self {
+        let (matching, other) = self.split(pred);
+        let rerolled = self
+            .clone()
+            .assert_unsoundness()
+            .scale_probabilities(Self::total(&matching.data));
+        PDF {
+            data: other.add_pointwise(&rerolled).data,
+        }
+    }
+
+    /// Keep rerolling outcomes matching the predicate, like "reroll until not 1".
+    ///
+    /// Returns an error when every outcome matches, as the rerolling would never end.
+    pub fn reroll_until(&self, pred: impl Fn(Sample) -> bool) -> Result<Self, LlDoiceError> {
+        let (_, other) = self.split(pred);
+        let other_total = Self::total(&other.data);
+        if other_total.is_zero() {
+            return Err(LlDoiceError::EndlessReroll);
+        }
+        // Divided before multiplying by the total, as bounded types like Fpp cannot hold the factor total / other_total
+        let total = Self::total(&self.data);
+        Ok(PDF {
+            data: other
+                .data
+                .into_iter()
+                .map(|(k, v)| (k, conditional(&v, &other_total) * &total))
+                .collect(),
+        })
+    }
+
+    /// Reroll outcomes matching the predicate once, and keep the higher of the two rolls.
+    pub fn reroll_keep_higher(&self, pred: impl Fn(Sample) -> bool) -> Self {
+        let (matching, mut result) = self.split(pred);
+        let this = self.clone().assert_unsoundness();
+        for (outcome, prob) in matching.data {
+            // The original roll, as a certainty
+            let original = PDF {
+                data: [(outcome, T::one())].into(),
+            };
+            let kept = [original, this.clone()].max().scale_probabilities(prob);
+            result = result.add_pointwise(&kept);
+        }
+        PDF { data: result.data }
+    }
+
+    /// Distribution of the number of successes when rolling a pool of n of this die, like `5d10>=8`.
+    pub fn count_successes(&self, n: usize, success: impl Fn(Sample) -> bool) -> Self {
+        self.count_successes_with(n, success, |_| false, |_| false)
+    }
+
+    /// Distribution of the number of successes when rolling a pool of n of this die,
+    /// where some outcomes count as two successes (`double`) and some subtract a success (`botch`).
+    ///
+    /// If an outcome matches several predicates, `double` takes precedence over `success`, which takes precedence over `botch`.
+    /// Each die independently falls into one of these four categories,
+    /// so the result follows a multinomial distribution, which is computed directly instead of through n convolutions.
+    pub fn count_successes_with(
+        &self,
+        n: usize,
+        success: impl Fn(Sample) -> bool,
+        double: impl Fn(Sample) -> bool,
+        botch: impl Fn(Sample) -> bool,
+    ) -> Self {
+        let (doubles, rest) = self.split(double);
+        let (successes, rest) = rest.split(success);
+        let (botches, _) = rest.split(botch);
+
+        // The categories are assigned one after another, each time to the dice that are left.
+        // This splits the multinomial distribution into a product of binomial ones.
+        let total = Self::total(&self.data);
+        let doubles = Self::total(&doubles.data);
+        let successes = Self::total(&successes.data);
+        let botches = Self::total(&botches.data);
+        let after_doubles = total.clone() - &doubles;
+        let after_successes = after_doubles.clone() - &successes;
+        let double_pmfs = binomial_pmfs(n, &conditional(&doubles, &total));
+        let success_pmfs = binomial_pmfs(n, &conditional(&successes, &after_doubles));
+        let botch_pmfs = binomial_pmfs(n, &conditional(&botches, &after_successes));
+        let scale = num::pow(total, n);
+
+        let mut data = BTreeMap::new();
+        for d in 0..=n {
+            for s in 0..=n - d {
+                for (b, botch_prob) in botch_pmfs[n - d - s].iter().enumerate() {
+                    let prob =
+                        scale.clone() * &double_pmfs[n][d] * &success_pmfs[n - d][s] * botch_prob;
+                    // Impossible combinations are skipped entirely
+                    if prob.is_zero() {
+                        continue;
+                    }
+                    data.entry(2 * d as Sample + s as Sample - b as Sample)
+                        .and_modify(|e| *e += &prob)
+                        .or_insert(prob);
+                }
+            }
+        }
+
+        PDF { data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numerics::Fpp;
+    use crate::test_utils::assert_data_close;
+
+    #[test]
+    fn explode() {
+        let d6 = PDF::<f64, true>::die(6);
+        let (exploded, truncated) = d6.explode(|x| x == 6, 2);
+
+        let mut expected = BTreeMap::new();
+        for k in (1..=5).chain(7..=11).chain(13..=18) {
+            let depth = (k - 1) / 6;
+            expected.insert(k, (1.0f64 / 6.0).powi(depth as i32 + 1));
+        }
+        assert_data_close(exploded.data(), &expected);
+        assert!((truncated - (1.0f64 / 6.0).powi(3)).abs() < 1e-12);
+        assert!(exploded.validate().is_ok());
+
+        // Separate dice each count as a success, while a compounded die counts once
+        let success = |x| x >= 5;
+        let (separate, truncated) = d6.explode_count_successes(1, |x| x == 6, success, 1);
+        let expected = BTreeMap::from([(0, 12.0 / 18.0), (1, 5.0 / 18.0), (2, 1.0 / 18.0)]);
+        assert_data_close(separate.data(), &expected);
+        assert!((truncated - 1.0 / 36.0).abs() < 1e-12);
+        let compounded = d6.explode(|x| x == 6, 1).0.count_successes(1, success);
+        let expected = BTreeMap::from([(0, 2.0 / 3.0), (1, 1.0 / 3.0)]);
+        assert_data_close(compounded.data(), &expected);
+
+        let (pool, truncated) = d6.explode_count_successes(3, |x| x == 6, success, 1);
+        assert!(pool.validate().is_ok());
+        assert!((truncated - (1.0 - (35.0f64 / 36.0).powi(3))).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reroll() {
+        let d6 = PDF::<f64, true>::die(6);
+
+        // Great Weapon Fighting: reroll 1s and 2s once
+        let gwf = d6.reroll_once(|x| x <= 2);
+        let expected: BTreeMap<Sample, f64> = (1..=6)
+            .map(|k| (k, if k <= 2 { 2.0 / 36.0 } else { 8.0 / 36.0 }))
+            .collect();
+        assert_data_close(gwf.data(), &expected);
+
+        let until = d6.reroll_until(|x| x == 1).unwrap();
+        let expected: BTreeMap<Sample, f64> = (2..=6).map(|k| (k, 0.2)).collect();
+        assert_data_close(until.data(), &expected);
+        assert_eq!(d6.reroll_until(|_| true), Err(LlDoiceError::EndlessReroll));
+        let fpp_until = PDF::<Fpp, true>::die(6).reroll_until(|x| x == 1).unwrap();
+        assert_data_close(fpp_until.to_f64().data(), &expected);
+
+        // Rerolling everything and keeping the higher is advantage
+        let mut adv = d6.clone();
+        adv.with_advantage(1);
+        assert_data_close(d6.reroll_keep_higher(|_| true).data(), adv.data());
+    }
+
+    #[test]
+    fn given() {
+        let d20 = PDF::<f64, true>::die(20);
+        let (not_one, p) = d20.given(|x| x != 1).unwrap();
+        let expected: BTreeMap<Sample, f64> = (2..=20).map(|k| (k, 1.0 / 19.0)).collect();
+        assert_data_close(not_one.data(), &expected);
+        assert!((p - 0.95).abs() < 1e-12);
+
+        let two_d6 = PDF::ntimes(2, &PDF::<f64, true>::die(6));
+        let (high, p) = two_d6.given_range(10..).unwrap();
+        let expected = BTreeMap::from([(10, 0.5), (11, 1.0 / 3.0), (12, 1.0 / 6.0)]);
+        assert_data_close(high.data(), &expected);
+        assert!((p - 1.0 / 6.0).abs() < 1e-12);
+
+        assert_eq!(d20.given_range(21..), Err(LlDoiceError::ZeroTotal));
+    }
+
+    #[test]
+    fn success_pool() {
+        let d10 = PDF::<f64, true>::die(10);
+
+        // 5d10>=8 is binomial with p = 0.3
+        let pool = d10.count_successes(5, |x| x >= 8);
+        let binom = [1.0, 5.0, 10.0, 10.0, 5.0, 1.0];
+        let expected: BTreeMap<Sample, f64> = (0..=5)
+            .map(|k| {
+                (
+                    k,
+                    binom[k as usize] * 0.3f64.powi(k as i32) * 0.7f64.powi(5 - k as i32),
+                )
+            })
+            .collect();
+        assert_data_close(pool.data(), &expected);
+
+        // 10s count double, 1s subtract a success
+        let pool = d10.count_successes_with(3, |x| x >= 8, |x| x == 10, |x| x == 1);
+        let mut expected = BTreeMap::new();
+        let value = |x: Sample| match x {
+            10 => 2,
+            8 | 9 => 1,
+            1 => -1,
+            _ => 0,
+        };
+        for a in 1..=10 {
+            for b in 1..=10 {
+                for c in 1..=10 {
+                    *expected
+                        .entry(value(a) + value(b) + value(c))
+                        .or_insert(0.0) += 0.001;
+                }
+            }
+        }
+        assert_data_close(pool.data(), &expected);
+    }
+
+    #[test]
+    fn penetrate() {
+        let d4 = PDF::<f64, true>::die(4);
+        let (penetrated, _) = d4.explode_penetrating(|x| x == 4, 1);
+
+        // 1-3 stop immediately, a 4 adds another d4 - 1
+        let expected: BTreeMap<Sample, f64> = (1..=3)
+            .map(|k| (k, 0.25))
+            .chain((4..=7).map(|k| (k, 1.0 / 16.0)))
+            .collect();
+        assert_data_close(penetrated.data(), &expected);
+    }
+}
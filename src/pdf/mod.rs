@@ -0,0 +1,1293 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::{Add, AddAssign, Bound, Div, Mul, MulAssign, Neg, RangeInclusive, Rem, Sub},
+};
+
+use num::{FromPrimitive, Num, One, ToPrimitive};
+
+use crate::LlDoiceError;
+
+mod checked;
+mod comparison;
+mod conversion;
+mod convolution;
+mod count;
+mod distance;
+mod mechanics;
+mod order;
+mod statistics;
+mod validation;
+
+pub use checked::{DivisionPolicy, Rounding, ZeroDivisor};
+pub use comparison::{Opposed, TieRule};
+pub use convolution::Convolve;
+pub use count::CountPdf;
+pub use validation::Precision;
+
+pub type Sample = isize;
+
+/// A discrete probability distribution, based on a BTreeMap.
+///
+/// # Soundness
+/// The type-level SOUND flag is used to keep track of whether it can be guaranteed that the distribution is mathematically sound.
+/// The requirements for 'soundness' are the following:
+/// - All probabilities must be between 0 and 1 inclusively
+/// - The sum of all probabilities must be within MAX_ERROR of 1, or exactly 1 for exact types like `BigRational`
+///
+/// Some operations leave the distribution in a state where soundness cannot be guaranteed,
+/// this can be seen in the return type of these operations (SOUND = false).
+/// Use the validate function to turn an unsound PDF back into a sound one.
+///
+/// # Optimality
+/// This may not be the single most efficient way of storing a PDF, but it is simple and easy to work with for now.
+/// It is likely that the BTreeMap will be swapped out for something else at some point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PDF<T, const SOUND: bool> {
+    data: BTreeMap<Sample, T>,
+}
+
+impl<T, const SOUND: bool> PDF<T, SOUND> {
+    pub fn new() -> PDF<T, false> {
+        PDF {
+            data: BTreeMap::new(),
+        }
+    }
+
+    pub fn data(&self) -> &BTreeMap<Sample, T> {
+        &self.data
+    }
+
+    /// Apply an offset to all outcomes.
+    pub fn offset(self, offset: Sample) -> Self {
+        Self {
+            data: self
+                .data
+                .into_iter()
+                .map(|(k, v)| (k + offset, v))
+                .collect(),
+        }
+    }
+}
+
+/// Shorthand for some of the trait bounds
+pub trait Number:
+    Num + FromPrimitive + PartialOrd + ToPrimitive + Clone + Convolve + Precision
+where
+    for<'a> Self: Add<&'a Self, Output = Self>,
+    Self: AddAssign<Self>,
+    for<'a> Self: AddAssign<&'a Self>,
+    for<'a> Self: Mul<&'a Self, Output = Self>,
+    for<'a> Self: MulAssign<&'a Self>,
+    for<'a> Self: Sub<&'a Self, Output = Self>,
+{
+}
+
+impl<T: Num + FromPrimitive + PartialOrd + ToPrimitive + Clone + Convolve + Precision> Number for T
+where
+    for<'a> Self: Add<&'a Self, Output = Self>,
+    Self: AddAssign<Self>,
+    for<'a> Self: AddAssign<&'a Self>,
+    for<'a> Self: Mul<&'a Self, Output = Self>,
+    for<'a> Self: MulAssign<&'a Self>,
+    for<'a> Self: Sub<&'a Self, Output = Self>,
+{
+}
+
+// Main impl for PDF where math with T is possible.
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    fn check_number(num: &T) -> bool {
+        *num >= T::zero() && *num <= T::one()
+    }
+
+    /// Sum of all probabilities.
+    fn total(data: &BTreeMap<Sample, T>) -> T {
+        data.values().fold(T::zero(), |acc, v| acc + v)
+    }
+
+    /// Sum of all values, or None when the sum does not fit in T.
+    fn checked_total<'a>(values: impl IntoIterator<Item = &'a T>) -> Option<T>
+    where
+        T: 'a,
+    {
+        values
+            .into_iter()
+            .try_fold(T::zero(), |acc, v| acc.try_add(v))
+    }
+
+    /// Sum of all values in f64, which can hold totals above 1 for bounded types like Fpp.
+    fn f64_total<'a>(values: impl IntoIterator<Item = &'a T>) -> f64
+    where
+        T: 'a,
+    {
+        values
+            .into_iter()
+            .map(|v| v.to_f64().expect("Number must be convertible to f64."))
+            .sum()
+    }
+
+    /// Maximum error allowed when checking the total probability of inexact types.
+    pub const MAX_ERROR: f64 = 0.01;
+    /// Check whether all entries are between 0 and 1, and whether the total probability is within tolerance of 1.
+    /// Exact types are compared to 1 exactly when the tolerance is 0.
+    fn check_total(data: &BTreeMap<Sample, T>, tolerance: f64) -> Result<(), LlDoiceError> {
+        if let Some((outcome, value)) = data.iter().find(|(_, v)| !Self::check_number(v)) {
+            return Err(LlDoiceError::ProbabilityOutOfRange {
+                outcome: *outcome,
+                value: value.to_f64().unwrap_or(f64::NAN),
+            });
+        }
+
+        Self::check_sum(data.values(), tolerance)
+    }
+
+    /// Check whether values sum to within tolerance of 1.
+    ///
+    /// The sum must also fit in T, as sound operations like `cumulative` add up the probabilities in T.
+    /// Exact types are compared to 1 exactly when the tolerance is 0.
+    /// Otherwise the sum is compared in f64, which also describes totals that do not fit in T.
+    fn check_sum<'a>(
+        values: impl IntoIterator<Item = &'a T> + Clone,
+        tolerance: f64,
+    ) -> Result<(), LlDoiceError>
+    where
+        T: 'a,
+    {
+        let total = Self::f64_total(values.clone());
+        let total_ok = match Self::checked_total(values) {
+            None => false,
+            Some(exact) if T::EXACT && tolerance == 0.0 => exact.is_one(),
+            Some(_) => (1.0f64 - total).abs() <= tolerance,
+        };
+        if !total_ok {
+            return Err(LlDoiceError::InvalidTotal { total, tolerance });
+        }
+        Ok(())
+    }
+
+    /// The tolerance used by `validate`.
+    fn default_tolerance() -> f64 {
+        if T::EXACT {
+            0.0
+        } else {
+            Self::MAX_ERROR
+        }
+    }
+
+    /// Check whether the PDF is sound, with a total that is within MAX_ERROR of 1, or exactly 1 for exact types.
+    pub fn validate(self) -> Result<PDF<T, true>, LlDoiceError> {
+        self.validate_within(Self::default_tolerance())
+    }
+
+    /// Check whether the PDF is sound, with a total that is within the given tolerance of 1.
+    ///
+    /// The error describes the first problem found: either an outcome with a probability outside of [0, 1],
+    /// or the actual total.
+    pub fn validate_within(self, tolerance: f64) -> Result<PDF<T, true>, LlDoiceError> {
+        Self::check_total(&self.data, tolerance)?;
+        Ok(PDF { data: self.data })
+    }
+
+    /// Divide all probabilities by the total probability, so that they sum to 1.
+    ///
+    /// Returns an error when the total is zero or does not fit in T, or when any probability is negative.
+    pub fn normalize(self) -> Result<PDF<T, true>, LlDoiceError> {
+        self.normalize_with_total().map(|(pdf, _)| pdf)
+    }
+
+    /// Like `normalize`, but also returns the total probability that was rescaled to 1.
+    pub fn normalize_with_total(self) -> Result<(PDF<T, true>, T), LlDoiceError> {
+        if let Some((outcome, value)) = self.data.iter().find(|(_, v)| **v < T::zero()) {
+            return Err(LlDoiceError::ProbabilityOutOfRange {
+                outcome: *outcome,
+                value: value.to_f64().unwrap_or(f64::NAN),
+            });
+        }
+        let total =
+            Self::checked_total(self.data.values()).ok_or_else(|| LlDoiceError::TotalOverflow {
+                total: Self::f64_total(self.data.values()),
+            })?;
+        if total.is_zero() {
+            return Err(LlDoiceError::ZeroTotal);
+        }
+
+        let data = self
+            .data
+            .into_iter()
+            .map(|(k, v)| (k, order::conditional(&v, &total)))
+            .collect();
+        Ok((PDF { data }, total))
+    }
+
+    /// Simply assumes that the PDF is sound.
+    ///
+    /// The PDF will still be validated (causing a panic on failure) in debug mode.
+    ///
+    /// # Safety
+    /// Is only safe if the PDF is actually sound.
+    pub unsafe fn assert_soundness(self) -> PDF<T, true> {
+        #[cfg(not(debug_assertions))]
+        return PDF { data: self.data };
+        #[cfg(debug_assertions)]
+        return {
+            self.validate()
+                .expect("Supposedly sound PDF turned out to be unsound.")
+        };
+    }
+
+    /// Simply assumes that the PDF could be unsound.
+    /// Should mosly be useful when trying to store a sound PDF alongside unsound ones in a data structure.
+    pub fn assert_unsoundness(self) -> PDF<T, false> {
+        PDF { data: self.data }
+    }
+
+    /// Convolute the PDF with itself n times, resulting in the sum of n + 1 copies.
+    pub fn autoconvolute(self, n: usize) -> Self {
+        self.repeat_sum(n + 1)
+    }
+
+    /// Sum n independent copies of this PDF.
+    ///
+    /// Uses exponentiation by squaring, so only O(log n) convolutions are needed.
+    /// The sum of zero copies is always 0.
+    pub fn repeat_sum(self, mut n: usize) -> Self {
+        let mut result: Option<Self> = None;
+        let mut base = self;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = Some(match result {
+                    Some(result) => &result + &base,
+                    None => base.clone(),
+                });
+            }
+            n >>= 1;
+            if n > 0 {
+                base = &base + &base;
+            }
+        }
+        result.unwrap_or_else(|| PDF {
+            data: [(0, T::one())].into(),
+        })
+    }
+
+    /// Roll n of the given die and sum the results, like NdX.
+    pub fn ntimes(n: usize, die: &Self) -> Self {
+        die.clone().repeat_sum(n)
+    }
+
+    /// Transform every outcome, like halving damage on a successful save.
+    /// Outcomes that end up on the same value are merged.
+    pub fn map(self, f: impl Fn(Sample) -> Sample) -> Self {
+        let mut data = BTreeMap::new();
+        for (k, v) in self.data {
+            data.entry(f(k)).and_modify(|e| *e += &v).or_insert(v);
+        }
+        PDF { data }
+    }
+
+    /// Apply a scale to all outcomes.
+    /// Scaling by 0 merges all outcomes into 0, so no probability is lost.
+    pub fn scale(self, scale: Sample) -> Self {
+        self.map(|k| k * scale)
+    }
+
+    /// Divide all outcomes by a constant, rounding toward zero like integer division.
+    ///
+    /// # Panics
+    /// Panics when the divisor is 0.
+    pub fn divide(self, divisor: Sample) -> Self {
+        assert!(divisor != 0, "Cannot divide outcomes by zero.");
+        self.map(|k| k / divisor)
+    }
+
+    /// The remainder of all outcomes after dividing by a constant, like d100 % 10.
+    /// Like the % operator, the remainder has the sign of the outcome.
+    ///
+    /// # Panics
+    /// Panics when the modulus is 0.
+    /// Use `checked_modulo` to get an error instead.
+    pub fn modulo(self, modulus: Sample) -> Self {
+        assert!(
+            modulus != 0,
+            "Cannot take the remainder of a division by zero."
+        );
+        self.map(|k| k % modulus)
+    }
+
+    /// The absolute value of all outcomes, like |2d6 - 7|.
+    ///
+    /// # Panics
+    /// Overflows when an outcome is `Sample::MIN`, which panics in debug builds.
+    pub fn abs(self) -> Self {
+        self.map(|k| k.abs())
+    }
+
+    /// Follow every outcome by a roll that depends on it, like "roll a d4, then roll that many d6".
+    ///
+    /// Uses the law of total probability: P(Y = y) = Σ P(X = x) * P(Y = y | X = x).
+    pub fn flat_map(&self, f: impl Fn(Sample) -> PDF<T, SOUND>) -> PDF<T, SOUND> {
+        let mut data = BTreeMap::new();
+        for (outcome, prob) in &self.data {
+            for (k, v) in f(*outcome).data {
+                let weighted = v * prob;
+                data.entry(k)
+                    .and_modify(|e| *e += &weighted)
+                    .or_insert(weighted);
+            }
+        }
+        PDF { data }
+    }
+
+    /// Scale all probabilities by a factor.
+    pub fn scale_probabilities(mut self, factor: T) -> PDF<T, false> {
+        for v in self.data.values_mut() {
+            *v *= &factor;
+        }
+
+        PDF { data: self.data }
+    }
+
+    /// Add all probabilities in the other PDF to this one.
+    pub fn add_pointwise(mut self, other: &Self) -> PDF<T, false> {
+        for (k, v) in other.data.iter() {
+            self.data
+                .entry(*k)
+                .and_modify(|e| *e += v)
+                .or_insert_with(|| v.clone());
+        }
+        PDF { data: self.data }
+    }
+
+    pub fn square_probabilities(mut self) -> PDF<T, false> {
+        for v in self.data.values_mut() {
+            *v *= &v.clone();
+        }
+        PDF { data: self.data }
+    }
+
+    /// Makes all probabilities equal 1- itself
+    pub fn invert_probabilities(&mut self) {
+        for v in self.data.values_mut() {
+            *v = T::one() - &*v;
+        }
+    }
+
+    /// Return the cumulative version of this PDf.
+    pub fn cumulative(&self) -> PDF<T, false> {
+        PDF {
+            data: self
+                .data
+                .iter()
+                .scan(T::zero(), |state, (k, v)| {
+                    *state += v;
+                    Some((*k, state.clone()))
+                })
+                .collect(),
+        }
+    }
+
+    /// Return the reverse cumulative version of this PDf.
+    /// P(X<x)
+    pub fn cumulative_exclusive(&self) -> PDF<T, false> {
+        PDF {
+            data: self
+                .data
+                .iter()
+                .scan(T::zero(), |state, (k, v)| {
+                    let val = state.clone();
+                    *state += v;
+                    Some((*k, val))
+                })
+                .collect(),
+        }
+    }
+
+    /// Return the reverse cumulative version of this PDf.
+    /// P(X>=x)
+    pub fn rev_cumulative(&self) -> PDF<T, false> {
+        PDF {
+            data: self
+                .data
+                .iter()
+                .rev()
+                .scan(T::zero(), |state, (k, v)| {
+                    *state += v;
+                    Some((*k, state.clone()))
+                })
+                .collect(),
+        }
+    }
+
+    /// Return the reverse cumulative version of this PDf.
+    /// P(X>x)
+    pub fn rev_cumulative_exclusive(&self) -> PDF<T, false> {
+        PDF {
+            data: self
+                .data
+                .iter()
+                .rev()
+                .scan(T::zero(), |state, (k, v)| {
+                    let val = state.clone();
+                    *state += v;
+                    Some((*k, val))
+                })
+                .collect(),
+        }
+    }
+
+    /// Apply n levels of advantage: roll n + 1 times and keep the highest.
+    pub fn with_advantage(&mut self, n: usize) {
+        // Naive implementation
+        // let cumulative: Vec<_> = self
+        //     .data
+        //     .values()
+        //     // Convert it to P(X<x)^(n+1)
+        //     .scan(T::zero(), |state, v| {
+        //         let tmp = state.clone();
+        //         *state += v;
+        //         Some(num::pow(tmp, n as usize))
+        //     })
+        //     // Add trailing 1
+        //     .chain([T::one()])
+        //     .collect();
+
+        // for (i, v) in self.data.values_mut().enumerate() {
+        //     *v = cumulative[i + 1].clone() - &cumulative[i];
+        // }
+
+        // Implementation that allocates no additional buffers
+        let mut one = [T::one()];
+        // Convert it to P(X<x)^(n+1) with a trailing 1
+        let mut iter = self
+            .data
+            .values_mut()
+            // Convert it to P(X<x)^(n+1)
+            .scan(T::zero(), |state, v| {
+                let tmp = state.clone();
+                *state += &*v;
+                *v = num::pow(tmp, n + 1);
+                Some(v)
+            })
+            // Add trailing 1
+            .chain(one.iter_mut())
+            .peekable();
+
+        // Then, collapse it into the final distribution
+        // The trailing 1 itself is left alone.
+        while let Some(first) = iter.next() {
+            if let Some(next) = iter.peek() {
+                *first = (**next).clone() - &*first;
+            }
+        }
+    }
+
+    /// Apply n levels of disadvantage: roll n + 1 times and keep the lowest.
+    ///
+    /// Mirrors `with_advantage`, but works from the highest outcome down using P(X>x).
+    pub fn with_disadvantage(&mut self, n: usize) {
+        let mut one = [T::one()];
+        let mut iter = self
+            .data
+            .values_mut()
+            .rev()
+            // Convert it to P(X>x)^(n+1)
+            .scan(T::zero(), |state, v| {
+                let tmp = state.clone();
+                *state += &*v;
+                *v = num::pow(tmp, n + 1);
+                Some(v)
+            })
+            // Add the leading 1
+            .chain(one.iter_mut())
+            .peekable();
+
+        // Then, collapse it into the final distribution
+        while let Some(first) = iter.next() {
+            if let Some(next) = iter.peek() {
+                *first = (**next).clone() - &*first;
+            }
+        }
+    }
+
+    /// Apply a signed level of advantage, where negative levels mean disadvantage.
+    pub fn with_advantage_level(&mut self, level: i32) {
+        match level {
+            0 => {}
+            1.. => self.with_advantage(level.unsigned_abs() as usize),
+            _ => self.with_disadvantage(level.unsigned_abs() as usize),
+        }
+    }
+
+    pub fn get_nearest_below(&self, bound: Sample) -> Option<(&Sample, &T)> {
+        self.data.upper_bound(Bound::Included(&bound)).peek_prev()
+    }
+
+    pub fn get_value_below(&self, bound: Sample) -> T {
+        self.data
+            .upper_bound(Bound::Included(&bound))
+            .peek_prev()
+            .map(|(_, v)| v)
+            .cloned()
+            .or_else(|| Some(T::zero()))
+            .unwrap()
+    }
+
+    pub fn get_nearest_above(&self, bound: Sample) -> Option<(&Sample, &T)> {
+        self.data.lower_bound(Bound::Included(&bound)).next()
+    }
+
+    pub fn get_value_above(&self, bound: Sample) -> T {
+        self.data
+            .lower_bound(Bound::Included(&bound))
+            .next()
+            .map(|(_, v)| v)
+            .cloned()
+            .or_else(|| Some(T::zero()))
+            .unwrap()
+    }
+
+    pub fn trim_zeroes(&mut self) {
+        self.data.retain(|_, v| !v.is_zero());
+    }
+}
+
+/// Distribution of the maximum or minimum of several independent, possibly differently shaped PDFs.
+///
+/// Like "take the higher of a d20 and a d12".
+/// See `maths/advantage.typ` for the derivation.
+pub trait MinMaxPDF: IntoIterator {
+    /// P(max = x) = Π P(X_i <= x) - Π P(X_i < x)
+    ///
+    /// # Panics
+    /// Panics when there are no PDFs.
+    fn max(self) -> Self::Item;
+    /// P(min = x) = Π P(X_i >= x) - Π P(X_i > x)
+    ///
+    /// # Panics
+    /// Panics when there are no PDFs.
+    fn min(self) -> Self::Item;
+}
+
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    /// Combines several PDFs by sweeping over all of their outcomes,
+    /// where P(X = x) = Π inclusive_i(x) - Π exclusive_i(x).
+    ///
+    /// `inclusive` and `exclusive` are cumulative versions of each PDF, which only contain the PDF's own outcomes.
+    /// `inclusive_at` looks up the inclusive value at an outcome that is not necessarily in the PDF.
+    fn sweep_extreme(
+        pdfs: Vec<Self>,
+        inclusive_of: impl Fn(&Self) -> PDF<T, false>,
+        exclusive_of: impl Fn(&Self) -> PDF<T, false>,
+        inclusive_at: impl Fn(&PDF<T, false>, Sample) -> T,
+    ) -> Self {
+        assert!(!pdfs.is_empty(), "Need at least one PDF.");
+
+        let outcomes: BTreeSet<Sample> = pdfs.iter().flat_map(|p| p.data.keys().copied()).collect();
+        let inclusive: Vec<PDF<T, false>> = pdfs.iter().map(&inclusive_of).collect();
+        let exclusive: Vec<PDF<T, false>> = pdfs.iter().map(&exclusive_of).collect();
+
+        let mut result = PDF {
+            data: outcomes
+                .into_iter()
+                .map(|x| {
+                    let (incl, excl) = inclusive.iter().zip(exclusive.iter()).fold(
+                        (T::one(), T::one()),
+                        |(incl_acc, excl_acc), (incl, excl)| {
+                            let incl = inclusive_at(incl, x);
+                            // Without mass at x, the exclusive and inclusive values are equal
+                            let excl = excl.data.get(&x).cloned().unwrap_or_else(|| incl.clone());
+                            (incl_acc * &incl, excl_acc * &excl)
+                        },
+                    );
+                    (x, incl - &excl)
+                })
+                .collect(),
+        };
+        result.trim_zeroes();
+        result
+    }
+}
+
+impl<It, T, const SOUND: bool> MinMaxPDF for It
+where
+    It: IntoIterator<Item = PDF<T, SOUND>>,
+    T: Number,
+{
+    fn max(self) -> Self::Item {
+        PDF::sweep_extreme(
+            self.into_iter().collect(),
+            PDF::cumulative,
+            PDF::cumulative_exclusive,
+            PDF::get_value_below,
+        )
+    }
+
+    fn min(self) -> Self::Item {
+        PDF::sweep_extreme(
+            self.into_iter().collect(),
+            PDF::rev_cumulative,
+            PDF::rev_cumulative_exclusive,
+            PDF::get_value_above,
+        )
+    }
+}
+
+impl<T: Number> PDF<T, true> {
+    /// A uniform distribution over all outcomes in the range.
+    ///
+    /// # Panics
+    /// Panics when the range is empty, as there is no outcome to give the probability to.
+    pub fn uniform(range: RangeInclusive<Sample>) -> PDF<T, true> {
+        assert!(!range.is_empty(), "Cannot be uniform over an empty range.");
+        let count = range.clone().count();
+        // Bounded types like Fpp cannot represent the count itself, so those go through f64.
+        // 1 / count can be rounded up in f64, which would push the total above 1, so it is rounded down instead.
+        let prob = match T::from_usize(count) {
+            Some(count) => T::one() / count,
+            None => T::from_f64(1.0 / count as f64 * (1.0 - f64::EPSILON))
+                .expect("Probability must fit in T."),
+        };
+        PDF {
+            data: range.map(|k| (k, prob.clone())).collect(),
+        }
+    }
+
+    /// A fair die with outcomes 1 to sides.
+    ///
+    /// # Panics
+    /// Panics when the die has no sides.
+    pub fn die(sides: Sample) -> PDF<T, true> {
+        Self::uniform(1..=sides)
+    }
+
+    /// A mixture of several PDFs, where each one is picked with the probability given by its weight.
+    ///
+    /// Like an attack that misses, hits or crits, each with their own damage distribution.
+    /// The weights must be valid probabilities, and must sum to 1 in the same way `validate` requires.
+    pub fn mixture(
+        components: impl IntoIterator<Item = (T, PDF<T, true>)>,
+    ) -> Result<PDF<T, true>, LlDoiceError> {
+        let components: Vec<_> = components.into_iter().collect();
+        if let Some((index, (weight, _))) = components
+            .iter()
+            .enumerate()
+            .find(|(_, (weight, _))| !Self::check_number(weight))
+        {
+            return Err(LlDoiceError::InvalidWeight {
+                index,
+                value: weight.to_f64().unwrap_or(f64::NAN),
+            });
+        }
+        // Checked before mixing, as bounded types cannot hold the probabilities of over-unit weights
+        Self::check_sum(
+            components.iter().map(|(weight, _)| weight),
+            Self::default_tolerance(),
+        )?;
+
+        let mut data: BTreeMap<Sample, T> = BTreeMap::new();
+        for (weight, pdf) in components {
+            for (k, v) in pdf.data {
+                let prob = v * &weight;
+                data.entry(k).and_modify(|e| *e += &prob).or_insert(prob);
+            }
+        }
+
+        Ok(PDF { data })
+    }
+}
+
+impl<T: One> Default for PDF<T, true> {
+    fn default() -> PDF<T, true> {
+        PDF {
+            data: [(0, T::one())].into(),
+        }
+    }
+}
+
+impl<T, const SOUND: bool> From<PDF<T, SOUND>> for BTreeMap<Sample, T> {
+    /// Allow one to extract the data from the PDF.
+    fn from(value: PDF<T, SOUND>) -> Self {
+        value.data
+    }
+}
+
+impl<T> From<BTreeMap<Sample, T>> for PDF<T, false> {
+    /// Allow one to construct a potentially unsound PDF from raw data.
+    fn from(value: BTreeMap<Sample, T>) -> Self {
+        PDF { data: value }
+    }
+}
+
+// Arithmetic implementations for PDF.
+/// The sum of two independent rolls.
+///
+/// Floats are convolved with the FFT for large PDFs, see `Convolve for f64`.
+/// Tiny tail probabilities are recomputed directly there, so their relative error stays around `1e-9`,
+/// but like any float arithmetic, probabilities below `f64::MIN_POSITIVE` lose precision or become 0.
+impl<T: Number, const SOUND: bool> Add<&PDF<T, SOUND>> for &PDF<T, SOUND> {
+    type Output = PDF<T, SOUND>;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, rhs: &PDF<T, SOUND>) -> Self::Output {
+        PDF {
+            data: convolution::convolve(&self.data, &rhs.data),
+        }
+    }
+}
+
+impl<T: Number, const SOUND: bool> Sub<&PDF<T, SOUND>> for &PDF<T, SOUND> {
+    type Output = PDF<T, SOUND>;
+
+    /// The difference of two independent rolls, which is the sum with the negated rhs.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, rhs: &PDF<T, SOUND>) -> Self::Output {
+        self + &-rhs
+    }
+}
+
+/// Negates every outcome.
+///
+/// # Panics
+/// Overflows when an outcome is `Sample::MIN`, which panics in debug builds.
+/// Use `checked_neg` to get an error instead.
+impl<T: Number, const SOUND: bool> Neg for &PDF<T, SOUND> {
+    type Output = PDF<T, SOUND>;
+
+    fn neg(self) -> Self::Output {
+        PDF {
+            data: self.data.iter().map(|(k, v)| (-k, v.clone())).collect(),
+        }
+    }
+}
+
+impl<T: Number, const SOUND: bool> Mul<&PDF<T, SOUND>> for &PDF<T, SOUND> {
+    type Output = PDF<T, SOUND>;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: &PDF<T, SOUND>) -> Self::Output {
+        let mut data = BTreeMap::new();
+        for (outcome, prob) in self.data.iter() {
+            for (k, v) in rhs.data.iter() {
+                data.entry(outcome * k)
+                    .and_modify(|e| *e += prob.clone() * v)
+                    .or_insert_with(|| prob.clone() * v);
+            }
+        }
+        PDF { data }
+    }
+}
+
+/// Division rounds toward zero.
+///
+/// # Panics
+/// Panics when the rhs has 0 as an outcome.
+/// Use `checked_div` to get an error or drop those rolls instead.
+impl<T: Number, const SOUND: bool> Div for &PDF<T, SOUND> {
+    type Output = PDF<T, SOUND>;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self::Output {
+        let mut data = BTreeMap::new();
+        for (outcome, prob) in self.data.iter() {
+            for (k, v) in rhs.data.iter() {
+                data.entry(outcome / k)
+                    .and_modify(|e| *e += prob.clone() * v)
+                    .or_insert_with(|| prob.clone() * v);
+            }
+        }
+        PDF { data }
+    }
+}
+
+/// The remainder has the sign of the lhs, like the % operator on integers.
+///
+/// # Panics
+/// Panics when the rhs has 0 as an outcome, or when `Sample::MIN` is divided by -1.
+/// Use `checked_rem` to get an error or drop the rolls with a zero divisor instead.
+impl<T: Number, const SOUND: bool> Rem for &PDF<T, SOUND> {
+    type Output = PDF<T, SOUND>;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn rem(self, rhs: Self) -> Self::Output {
+        let mut data = BTreeMap::new();
+        for (outcome, prob) in self.data.iter() {
+            for (k, v) in rhs.data.iter() {
+                data.entry(outcome % k)
+                    .and_modify(|e| *e += prob.clone() * v)
+                    .or_insert_with(|| prob.clone() * v);
+            }
+        }
+        PDF { data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numerics::{Fpp, LogProb, ToFpp};
+    use crate::test_utils::{assert_data_close, ratio};
+    use num::{BigInt, BigRational, Signed};
+
+    /// Sums n dice by enumerating every possible roll.
+    fn brute_force_sum(n: u32, sides: Sample) -> BTreeMap<Sample, f64> {
+        let total = (sides as usize).pow(n);
+        let mut data = BTreeMap::new();
+        for roll in 0..total {
+            let sum: Sample = (0..n)
+                .map(|i| (roll / (sides as usize).pow(i)) % sides as usize)
+                .map(|face| face as Sample + 1)
+                .sum();
+            *data.entry(sum).or_insert(0.0) += 1.0 / total as f64;
+        }
+        data
+    }
+
+    #[test]
+    fn repeat_sum() {
+        let d4 = PDF::<f64, true>::die(4);
+        for n in 1..=7 {
+            assert_data_close(PDF::ntimes(n, &d4).data(), &brute_force_sum(n as u32, 4));
+        }
+        assert_eq!(d4.clone().repeat_sum(0), PDF::default());
+        assert_eq!(d4.clone().autoconvolute(2), d4.repeat_sum(3));
+    }
+
+    #[test]
+    fn disadvantage() {
+        let d20 = PDF::<f64, true>::die(20);
+        let two_d6 = PDF::ntimes(2, &PDF::<f64, true>::die(6)).offset(3);
+        for pdf in [d20, two_d6] {
+            for n in 0..4 {
+                let mut dis = pdf.clone();
+                dis.with_disadvantage(n);
+
+                // Disadvantage is the negated advantage of the negated distribution
+                let mut adv = pdf.clone().scale(-1);
+                adv.with_advantage(n);
+                assert_data_close(dis.data(), adv.scale(-1).data());
+
+                // And the minimum of n + 1 copies
+                let copies = vec![pdf.clone(); n + 1];
+                assert_data_close(dis.data(), copies.min().data());
+            }
+        }
+
+        let mut level = PDF::<f64, true>::die(20);
+        level.with_advantage_level(-2);
+        let mut dis = PDF::<f64, true>::die(20);
+        dis.with_disadvantage(2);
+        assert_eq!(level, dis);
+    }
+
+    /// Runs the PDF operations on T, and compares the results against f64.
+    fn suite<T: Number>() {
+        fn compare<T: Number, const A: bool, const B: bool>(a: &PDF<T, A>, b: &PDF<f64, B>) {
+            let outcomes: BTreeSet<_> = a.data.keys().chain(b.data.keys()).collect();
+            for k in outcomes {
+                let x = a.data.get(k).map_or(0.0, |v| v.to_f64().unwrap());
+                let y = b.data.get(k).copied().unwrap_or(0.0);
+                assert!((x - y).abs() < 1e-5, "{k}: {x} != {y}");
+            }
+        }
+
+        let d6 = PDF::<T, true>::die(6);
+        let d12 = PDF::<T, true>::die(12);
+        let d20 = PDF::<T, true>::die(20);
+        let d40 = PDF::<T, true>::die(40);
+        let f_d6 = PDF::<f64, true>::die(6);
+        let f_d12 = PDF::<f64, true>::die(12);
+        let f_d20 = PDF::<f64, true>::die(20);
+        let f_d40 = PDF::<f64, true>::die(40);
+
+        // Large enough to take the fast convolution path where available
+        compare(&PDF::ntimes(3, &d40), &PDF::ntimes(3, &f_d40));
+        compare(&(&d6 + &d20), &(&f_d6 + &f_d20));
+        compare(&(&d6 * &d12), &(&f_d6 * &f_d12));
+
+        let (mut adv, mut f_adv) = (d20.clone(), f_d20.clone());
+        adv.with_advantage(2);
+        f_adv.with_advantage(2);
+        compare(&adv, &f_adv);
+        let (mut dis, mut f_dis) = (d20.clone(), f_d20.clone());
+        dis.with_disadvantage(1);
+        f_dis.with_disadvantage(1);
+        compare(&dis, &f_dis);
+
+        compare(&d20.cumulative(), &f_d20.cumulative());
+        compare(&d20.cumulative_exclusive(), &f_d20.cumulative_exclusive());
+        compare(&d20.rev_cumulative(), &f_d20.rev_cumulative());
+        compare(
+            &d20.rev_cumulative_exclusive(),
+            &f_d20.rev_cumulative_exclusive(),
+        );
+
+        compare(&d6.keep_highest(4, 3), &f_d6.keep_highest(4, 3));
+        compare(
+            &[d20.clone(), d12.clone()].max(),
+            &[f_d20.clone(), f_d12].max(),
+        );
+        compare(
+            &d6.count_successes(5, |x| x >= 5),
+            &f_d6.count_successes(5, |x| x >= 5),
+        );
+
+        compare(
+            &d20.reroll_until(|x| x == 1).unwrap(),
+            &f_d20.reroll_until(|x| x == 1).unwrap(),
+        );
+        let (given, p) = d20.given(|x| x > 15).unwrap();
+        let (f_given, f_p) = f_d20.given(|x| x > 15).unwrap();
+        compare(&given, &f_given);
+        assert!((p.to_f64().unwrap() - f_p).abs() < 1e-5);
+
+        let weight = |w: f64| T::from_f64(w).unwrap();
+        let halved = d20.clone().scale_probabilities(weight(0.5));
+        compare(&halved.normalize().unwrap(), &f_d20);
+        // 0.75 is computed from 0.25, as converting both can round up to more than 1 in total
+        let quarter = weight(0.25);
+        let rest = T::one() - &quarter;
+        let mixed = PDF::mixture([(quarter, d6.clone()), (rest, d20.clone())]);
+        let f_mixed = PDF::mixture([(0.25, f_d6.clone()), (0.75, f_d20.clone())]);
+        compare(&mixed.unwrap(), &f_mixed.unwrap());
+
+        assert!(PDF::ntimes(3, &d12).assert_unsoundness().validate().is_ok());
+        assert!(adv.assert_unsoundness().validate().is_ok());
+    }
+
+    #[test]
+    fn suite_f64() {
+        suite::<f64>();
+    }
+
+    #[test]
+    fn suite_f32() {
+        suite::<f32>();
+    }
+
+    #[test]
+    fn suite_fpp() {
+        suite::<Fpp>();
+    }
+
+    #[test]
+    fn suite_log_prob() {
+        suite::<LogProb>();
+    }
+
+    #[test]
+    fn suite_big_rational() {
+        suite::<BigRational>();
+    }
+
+    #[test]
+    fn exact_rationals() {
+        let d6 = PDF::<BigRational, true>::die(6);
+
+        let four_d6 = PDF::ntimes(4, &d6);
+        assert_eq!(four_d6.data()[&24], ratio(1, 1296));
+        assert_eq!(PDF::ntimes(2, &d6).data()[&7], ratio(1, 6));
+
+        let mut adv = PDF::<BigRational, true>::die(20);
+        adv.with_advantage(1);
+        assert_eq!(adv.data()[&20], ratio(39, 400));
+        assert!(adv.assert_unsoundness().validate().is_ok());
+
+        // Within the f64 tolerance, but not exactly 1
+        let almost: BTreeMap<Sample, BigRational> =
+            [(1, ratio(1, 2)), (2, ratio(499, 1000))].into();
+        assert!(PDF::from(almost.clone()).validate().is_err());
+        let almost: BTreeMap<Sample, f64> = almost
+            .into_iter()
+            .map(|(k, v)| (k, v.to_f64().unwrap()))
+            .collect();
+        assert!(PDF::from(almost).validate().is_ok());
+    }
+
+    #[test]
+    fn conversions() {
+        let d6 = PDF::<BigRational, true>::die(6);
+        let f_d6 = d6.to_f64();
+        assert_data_close(f_d6.data(), PDF::<f64, true>::die(6).data());
+
+        // 1/2 and 1/4 are exactly representable everywhere
+        let exact: PDF<f64, false> = BTreeMap::from([(1, 0.5), (2, 0.25), (3, 0.25)]).into();
+        let rational = exact.to_rational();
+        assert!(rational.clone().validate().is_ok());
+        assert_eq!(rational.to_f64(), exact);
+
+        let fpp = f_d6.to_fpp().unwrap();
+        let back = fpp.to_rational();
+        for (a, b) in back.data().values().zip(d6.data().values()) {
+            assert!((a.clone() - b).abs() < ratio_epsilon());
+        }
+        assert!(PDF::<f64, false>::from(BTreeMap::from([(1, 1.5)]))
+            .to_fpp()
+            .is_err());
+
+        // Converted dice can be summed in Fpp, even when the f64 probabilities round up
+        for sides in 1..=200 {
+            let die = PDF::<f64, true>::die(sides).to_fpp().unwrap();
+            assert!(die.clone().assert_unsoundness().validate().is_ok());
+            die.cumulative();
+        }
+        let mut d2 = PDF::<f64, true>::die(2).to_fpp().unwrap();
+        d2.cumulative();
+        d2.with_advantage(1);
+        assert!((d2.data()[&2].to_f64().unwrap() - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn validation_errors() {
+        // Certainties and impossibilities are valid probabilities
+        let certain: PDF<f64, false> = BTreeMap::from([(1, 0.0), (2, 1.0)]).into();
+        assert!(certain.validate().is_ok());
+
+        let negative: PDF<f64, false> = BTreeMap::from([(1, 1.25), (2, -0.25)]).into();
+        assert_eq!(
+            negative.validate(),
+            Err(LlDoiceError::ProbabilityOutOfRange {
+                outcome: 1,
+                value: 1.25
+            })
+        );
+
+        let short: PDF<f64, false> = BTreeMap::from([(1, 0.5), (2, 0.4921875)]).into();
+        assert!(short.clone().validate().is_ok());
+        assert_eq!(
+            short.clone().validate_within(0.001),
+            Err(LlDoiceError::InvalidTotal {
+                total: 0.9921875,
+                tolerance: 0.001
+            })
+        );
+
+        // Exact types may still be given a tolerance
+        let rational = short.to_rational();
+        assert!(rational.clone().validate().is_err());
+        assert!(rational.validate_within(0.05).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "empty range")]
+    fn no_sides() {
+        PDF::<f64, true>::die(0);
+    }
+
+    #[test]
+    fn normalize() {
+        let d4 = PDF::<BigRational, true>::die(4);
+        let halved = d4.clone().scale_probabilities(ratio(1, 2));
+        let (normalized, total) = halved.normalize_with_total().unwrap();
+        assert_eq!(normalized, d4);
+        assert_eq!(total, ratio(1, 2));
+
+        let weights: PDF<f64, false> = BTreeMap::from([(1, 1.0), (2, 3.0)]).into();
+        assert_data_close(
+            weights.normalize().unwrap().data(),
+            &BTreeMap::from([(1, 0.25), (2, 0.75)]),
+        );
+        let fpp = PDF::<f64, true>::die(6)
+            .to_fpp()
+            .unwrap()
+            .square_probabilities();
+        assert!(fpp
+            .normalize()
+            .unwrap()
+            .assert_unsoundness()
+            .validate()
+            .is_ok());
+
+        // Fpp cannot hold a total above 1, which must be reported instead of saturating
+        let over_unit: PDF<Fpp, false> = BTreeMap::from([
+            (1, 0.75f64.to_fpp().unwrap()),
+            (2, 0.75f64.to_fpp().unwrap()),
+        ])
+        .into();
+        assert_eq!(
+            over_unit.clone().validate(),
+            Err(LlDoiceError::InvalidTotal {
+                total: 1.5,
+                tolerance: PDF::<Fpp, false>::MAX_ERROR
+            })
+        );
+        assert_eq!(
+            over_unit.normalize(),
+            Err(LlDoiceError::TotalOverflow { total: 1.5 })
+        );
+
+        // Two halves are one unit above Fpp::MAX, which is within tolerance in f64 but cannot be summed in Fpp
+        let half = 0.5f64.to_fpp().unwrap();
+        let just_over: PDF<Fpp, false> = BTreeMap::from([(1, half), (2, half)]).into();
+        assert!(matches!(
+            just_over.validate(),
+            Err(LlDoiceError::InvalidTotal { .. })
+        ));
+        let mut d2 = PDF::<Fpp, true>::die(2);
+        assert!(d2.clone().assert_unsoundness().validate().is_ok());
+        assert!((d2.cumulative().data()[&2].to_f64().unwrap() - 1.0).abs() < 1e-12);
+        d2.with_advantage(1);
+        assert!(d2.assert_unsoundness().validate().is_ok());
+
+        let empty: PDF<f64, false> = BTreeMap::from([(1, 0.0)]).into();
+        assert_eq!(empty.normalize(), Err(LlDoiceError::ZeroTotal));
+        let negative: PDF<f64, false> = BTreeMap::from([(1, 1.0), (2, -0.5)]).into();
+        assert_eq!(
+            negative.normalize(),
+            Err(LlDoiceError::ProbabilityOutOfRange {
+                outcome: 2,
+                value: -0.5
+            })
+        );
+    }
+
+    #[test]
+    fn mixture() {
+        let d8 = PDF::<BigRational, true>::die(8);
+        let miss = PDF::default();
+        let hit = d8.clone().offset(3);
+        let crit = PDF::ntimes(2, &d8).offset(3);
+        let attack = PDF::mixture([
+            (ratio(7, 20), miss),
+            (ratio(12, 20), hit),
+            (ratio(1, 20), crit.clone()),
+        ])
+        .unwrap();
+        assert_eq!(attack.data()[&0], ratio(7, 20));
+        assert_eq!(attack.data()[&4], ratio(12, 20 * 8));
+        assert_eq!(
+            attack.data()[&19],
+            crit.data()[&19].clone() / BigInt::from(20)
+        );
+        assert!(attack.assert_unsoundness().validate().is_ok());
+
+        assert_eq!(
+            PDF::mixture([(ratio(1, 2), d8.clone())]),
+            Err(LlDoiceError::InvalidTotal {
+                total: 0.5,
+                tolerance: 0.0
+            })
+        );
+        assert_eq!(
+            PDF::mixture([(ratio(3, 2), d8.clone()), (ratio(-1, 2), d8)]),
+            Err(LlDoiceError::InvalidWeight {
+                index: 0,
+                value: 1.5
+            })
+        );
+    }
+
+    #[test]
+    fn map_and_flat_map() {
+        let d6 = PDF::<f64, true>::die(6);
+        let halved = d6.clone().map(|x| x / 2);
+        let expected = BTreeMap::from([
+            (0, 1.0 / 6.0),
+            (1, 1.0 / 3.0),
+            (2, 1.0 / 3.0),
+            (3, 1.0 / 6.0),
+        ]);
+        assert_data_close(halved.data(), &expected);
+
+        // Roll a d4, then roll that many d6
+        let d4 = PDF::<f64, true>::die(4);
+        let pool = d4.flat_map(|n| PDF::ntimes(n as usize, &d6));
+        let mut expected = PDF::<f64, false>::new();
+        for n in 1..=4 {
+            expected = expected.add_pointwise(
+                &PDF::ntimes(n, &d6)
+                    .scale_probabilities(0.25)
+                    .assert_unsoundness(),
+            );
+        }
+        assert_data_close(pool.data(), expected.data());
+        assert!((pool.data()[&1] - 1.0 / 24.0).abs() < 1e-12);
+        assert!(pool.assert_unsoundness().validate().is_ok());
+    }
+
+    fn ratio_epsilon() -> BigRational {
+        BigRational::new(1.into(), 1_000_000_000_000i64.into())
+    }
+
+    #[test]
+    fn log_prob_tail() {
+        let d6 = PDF::<LogProb, true>::die(6);
+        let sum = PDF::ntimes(200, &d6);
+
+        // P(200d6 > 1100) is below the FFT's precision, so compare against the exact result
+        let tail = sum.rev_cumulative().get_value_above(1101);
+        let exact_tail = PDF::ntimes(200, &PDF::<BigRational, true>::die(6))
+            .rev_cumulative()
+            .get_value_above(1101)
+            .to_f64()
+            .unwrap();
+        assert!(exact_tail > 0.0);
+        assert!((tail.ln() - exact_tail.ln()).abs() < 1e-6);
+
+        // Rolling only sixes on 450d6 is not even representable as f64
+        let sum = PDF::ntimes(450, &d6);
+        assert!((sum.data()[&2700].ln() + 450.0 * 6f64.ln()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn min_max() {
+        let d20 = PDF::<f64, true>::die(20);
+        let d12 = PDF::<f64, true>::die(12);
+
+        let mut max = BTreeMap::new();
+        let mut min = BTreeMap::new();
+        for a in 1..=20 {
+            for b in 1..=12 {
+                *max.entry(a.max(b)).or_insert(0.0) += 1.0 / 240.0;
+                *min.entry(a.min(b)).or_insert(0.0) += 1.0 / 240.0;
+            }
+        }
+        assert_data_close([d20.clone(), d12.clone()].max().data(), &max);
+        assert_data_close([d20.clone(), d12].min().data(), &min);
+
+        // The maximum of identical dice is advantage
+        let mut adv = d20.clone();
+        adv.with_advantage(2);
+        assert_data_close(vec![d20.clone(), d20.clone(), d20].max().data(), adv.data());
+    }
+
+    #[test]
+    fn outcome_operators() {
+        let d4 = PDF::<f64, true>::die(4);
+        let d6 = PDF::<f64, true>::die(6);
+        let d20 = PDF::<f64, true>::die(20);
+
+        let mut expected = BTreeMap::new();
+        for a in 1..=20 {
+            for b in 1..=6 {
+                *expected.entry(a - b).or_insert(0.0) += 1.0 / 120.0;
+            }
+        }
+        assert_data_close((&d20 - &d6).data(), &expected);
+
+        let expected: BTreeMap<Sample, f64> = (-4..=-1).map(|k| (k, 0.25)).collect();
+        assert_data_close((-&d4).data(), &expected);
+
+        // |2d6 - 7|
+        let spread = PDF::ntimes(2, &d6).offset(-7).abs();
+        let expected = BTreeMap::from([
+            (0, 6.0 / 36.0),
+            (1, 10.0 / 36.0),
+            (2, 8.0 / 36.0),
+            (3, 6.0 / 36.0),
+            (4, 4.0 / 36.0),
+            (5, 2.0 / 36.0),
+        ]);
+        assert_data_close(spread.data(), &expected);
+
+        let d100 = PDF::<f64, true>::die(100);
+        let expected: BTreeMap<Sample, f64> = (0..=9).map(|k| (k, 0.1)).collect();
+        assert_data_close(d100.clone().modulo(10).data(), &expected);
+        assert_data_close((&d100 % &PDF::default().offset(10)).data(), &expected);
+        assert_data_close(
+            d100.divide(10).data(),
+            (&PDF::<f64, true>::die(100) / &PDF::default().offset(10)).data(),
+        );
+
+        // Merging outcomes keeps the PDF sound
+        assert!(d6.scale(0).assert_unsoundness().validate().is_ok());
+    }
+}
@@ -0,0 +1,188 @@
+//! Order statistics, like keeping the highest k of n dice.
+
+use std::collections::BTreeMap;
+
+use super::{Number, Sample, PDF};
+
+/// Returns a table where entry [r][c] is the probability that exactly c out of r dice succeed,
+/// when each of them succeeds with probability q.
+///
+/// Only probabilities are multiplied and added, so no entry ever exceeds 1.
+/// This keeps it usable for bounded probability types like `Fpp`, and exact for exact types.
+pub(super) fn binomial_pmfs<T: Number>(n: usize, q: &T) -> Vec<Vec<T>> {
+    let not_q = T::one() - q;
+    let mut rows: Vec<Vec<T>> = Vec::with_capacity(n + 1);
+    rows.push(vec![T::one()]);
+    for r in 1..=n {
+        let prev = &rows[r - 1];
+        let row = (0..=r)
+            .map(|c| {
+                let fail = prev.get(c).map_or(T::zero(), |p| p.clone() * &not_q);
+                let success = match c {
+                    0 => T::zero(),
+                    _ => prev[c - 1].clone() * q,
+                };
+                fail + &success
+            })
+            .collect();
+        rows.push(row);
+    }
+    rows
+}
+
+/// The probability of part, given whole: part / whole, clamped to [0, 1].
+///
+/// Rounding can make part slightly larger than whole, which would be invalid for bounded types like `Fpp`.
+pub(super) fn conditional<T: Number>(part: &T, whole: &T) -> T {
+    if whole.is_zero() {
+        T::zero()
+    } else if part >= whole {
+        T::one()
+    } else {
+        part.clone() / whole.clone()
+    }
+}
+
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    /// Distribution of the sum of the k dice that come first in outcome order, when rolling n of this die.
+    /// When descending is set, the highest outcomes come first.
+    ///
+    /// Works by assigning the outcomes to the dice one outcome at a time, in order.
+    /// The state after each outcome is the number of dice assigned so far and the sum of the kept ones,
+    /// so the dice never have to be enumerated.
+    /// Given that a die has not been assigned yet, it rolls the current outcome with probability
+    /// P(X = x) / P(X not yet assigned), so the number of dice that roll it is binomially distributed.
+    fn keep_first(&self, n: usize, k: usize, descending: bool) -> BTreeMap<Sample, T> {
+        assert!(k <= n, "Cannot keep more dice than are rolled.");
+
+        // Probability mass of the outcomes that have not been assigned yet
+        let mut unassigned = Self::total(&self.data);
+
+        // states[i] maps the sum of the kept dice to its probability, given that i dice are assigned
+        let mut states: Vec<BTreeMap<Sample, T>> = vec![BTreeMap::new(); n + 1];
+        states[0].insert(0, num::pow(unassigned.clone(), n));
+
+        let outcomes: Box<dyn Iterator<Item = (&Sample, &T)>> = if descending {
+            Box::new(self.data.iter().rev())
+        } else {
+            Box::new(self.data.iter())
+        };
+        for (outcome, prob) in outcomes {
+            if prob.is_zero() {
+                continue;
+            }
+            let pmfs = binomial_pmfs(n, &conditional(prob, &unassigned));
+            unassigned = unassigned - prob;
+
+            let mut next: Vec<BTreeMap<Sample, T>> = vec![BTreeMap::new(); n + 1];
+            for (assigned, sums) in states.iter().enumerate() {
+                let remaining = n - assigned;
+                for (sum, p) in sums {
+                    // Let c of the remaining dice roll this outcome
+                    for c in 0..=remaining {
+                        let kept = c.min(k.saturating_sub(assigned)) as Sample;
+                        let weight = p.clone() * &pmfs[remaining][c];
+                        next[assigned + c]
+                            .entry(sum + outcome * kept)
+                            .and_modify(|e| *e += &weight)
+                            .or_insert(weight);
+                    }
+                }
+            }
+            states = next;
+        }
+
+        states.swap_remove(n)
+    }
+
+    /// Distribution of the sum of the highest k dice, when rolling n of this die.
+    ///
+    /// # Panics
+    /// Panics when k > n.
+    pub fn keep_highest(&self, n: usize, k: usize) -> Self {
+        PDF {
+            data: self.keep_first(n, k, true),
+        }
+    }
+
+    /// Distribution of the sum of the lowest k dice, when rolling n of this die.
+    ///
+    /// # Panics
+    /// Panics when k > n.
+    pub fn keep_lowest(&self, n: usize, k: usize) -> Self {
+        PDF {
+            data: self.keep_first(n, k, false),
+        }
+    }
+
+    /// Distribution of the sum of n of this die, after dropping the highest d.
+    ///
+    /// # Panics
+    /// Panics when d > n.
+    pub fn drop_highest(&self, n: usize, d: usize) -> Self {
+        self.keep_lowest(
+            n,
+            n.checked_sub(d)
+                .expect("Cannot drop more dice than are rolled."),
+        )
+    }
+
+    /// Distribution of the sum of n of this die, after dropping the lowest d, like 4d6 drop lowest.
+    ///
+    /// # Panics
+    /// Panics when d > n.
+    pub fn drop_lowest(&self, n: usize, d: usize) -> Self {
+        self.keep_highest(
+            n,
+            n.checked_sub(d)
+                .expect("Cannot drop more dice than are rolled."),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_data_close;
+
+    /// Computes the distribution of keeping the highest or lowest k dice by enumerating every roll.
+    fn brute_force(n: u32, k: usize, sides: usize, highest: bool) -> BTreeMap<Sample, f64> {
+        let total = sides.pow(n);
+        let mut data = BTreeMap::new();
+        for roll in 0..total {
+            let mut faces: Vec<Sample> = (0..n)
+                .map(|i| ((roll / sides.pow(i)) % sides) as Sample + 1)
+                .collect();
+            faces.sort();
+            if highest {
+                faces.reverse();
+            }
+            let sum = faces.iter().take(k).sum();
+            *data.entry(sum).or_insert(0.0) += 1.0 / total as f64;
+        }
+        data
+    }
+
+    #[test]
+    fn four_d6_drop_lowest() {
+        let d6 = PDF::<f64, true>::die(6);
+        assert_data_close(d6.drop_lowest(4, 1).data(), &brute_force(4, 3, 6, true));
+    }
+
+    #[test]
+    fn keep_against_brute_force() {
+        let d4 = PDF::<f64, true>::die(4);
+        for n in 1..=5 {
+            for k in 0..=n {
+                assert_data_close(
+                    d4.keep_highest(n, k).data(),
+                    &brute_force(n as u32, k, 4, true),
+                );
+                assert_data_close(
+                    d4.keep_lowest(n, k).data(),
+                    &brute_force(n as u32, k, 4, false),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Summary statistics, like the mean, variance and quantiles.
+//!
+//! Outcomes are integers while probabilities can be any `Number`,
+//! and types like `Fpp` cannot represent values outside of [0, 1], so the moments are computed in f64.
+//! Probabilities are divided by the total, so unsound PDFs are treated as if they were normalized.
+
+use crate::DiscStats;
+
+use super::{Number, Sample, PDF};
+
+/// Sum using Neumaier's compensated summation, to keep the rounding error independent of the number of terms.
+fn stable_sum(values: impl IntoIterator<Item = f64>) -> f64 {
+    let mut sum = 0.0f64;
+    let mut compensation = 0.0f64;
+    for v in values {
+        let t = sum + v;
+        if sum.abs() >= v.abs() {
+            compensation += (sum - t) + v;
+        } else {
+            compensation += (v - t) + sum;
+        }
+        sum = t;
+    }
+    sum + compensation
+}
+
+impl<T: Number, const SOUND: bool> PDF<T, SOUND> {
+    /// All outcomes with a non-zero probability, along with their normalized probability as f64.
+    pub(super) fn weights(&self) -> Vec<(Sample, f64)> {
+        let total = Self::f64_total(self.data.values());
+        self.data
+            .iter()
+            .filter(|(_, v)| !v.is_zero())
+            .map(|(k, v)| {
+                let v = v.to_f64().expect("Number must be convertible to f64.");
+                (*k, v / total)
+            })
+            .collect()
+    }
+
+    /// The lowest outcome with a non-zero probability.
+    pub fn min_outcome(&self) -> Option<Sample> {
+        self.data
+            .iter()
+            .find(|(_, v)| !v.is_zero())
+            .map(|(k, _)| *k)
+    }
+
+    /// The highest outcome with a non-zero probability.
+    pub fn max_outcome(&self) -> Option<Sample> {
+        self.data
+            .iter()
+            .rev()
+            .find(|(_, v)| !v.is_zero())
+            .map(|(k, _)| *k)
+    }
+
+    /// E[(X - center)^k], which is NaN for an empty PDF.
+    fn moment_about(&self, center: f64, k: i32) -> f64 {
+        let weights = self.weights();
+        if weights.is_empty() {
+            return f64::NAN;
+        }
+        stable_sum(
+            weights
+                .into_iter()
+                .map(|(x, p)| p * (x as f64 - center).powi(k)),
+        )
+    }
+
+    /// The k-th raw moment, E[X^k].
+    ///
+    /// Like all moments, this is NaN for an empty PDF.
+    pub fn raw_moment(&self, k: i32) -> f64 {
+        self.moment_about(0.0, k)
+    }
+
+    /// The k-th central moment, E[(X - mean)^k].
+    ///
+    /// Computed around the mean directly, instead of from the raw moments,
+    /// which would cancel catastrophically for distributions far away from 0.
+    pub fn central_moment(&self, k: i32) -> f64 {
+        self.moment_about(self.mean(), k)
+    }
+
+    /// The expected value.
+    pub fn mean(&self) -> f64 {
+        self.raw_moment(1)
+    }
+
+    pub fn variance(&self) -> f64 {
+        self.central_moment(2)
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// The third standardized moment, which is positive when the right tail is longer.
+    pub fn skewness(&self) -> f64 {
+        self.central_moment(3) / self.variance().powf(1.5)
+    }
+
+    /// The fourth standardized moment.
+    /// This is not the excess kurtosis, so a normal distribution has a kurtosis of 3.
+    pub fn kurtosis(&self) -> f64 {
+        self.central_moment(4) / self.variance().powi(2)
+    }
+
+    /// The smallest outcome x for which P(X <= x) >= p.
+    ///
+    /// The comparison is done in T, so exact types give exact quantiles.
+    /// Returns None when p is not between 0 and 1, or when the PDF is empty.
+    pub fn quantile(&self, p: f64) -> Option<Sample> {
+        if !(0.0..=1.0).contains(&p) {
+            return None;
+        }
+        let target = T::from_f64(p)? * &Self::total(&self.data);
+        let mut cumulative = T::zero();
+        for (k, v) in self.data.iter().filter(|(_, v)| !v.is_zero()) {
+            cumulative += v;
+            if cumulative >= target {
+                return Some(*k);
+            }
+        }
+        // Rounding can leave the total just below the target when p is 1
+        self.max_outcome()
+    }
+
+    /// The lower median, which is the smallest outcome x for which P(X <= x) >= 0.5.
+    pub fn median(&self) -> Option<Sample> {
+        self.quantile(0.5)
+    }
+
+    /// All outcomes that share the highest probability, in ascending order.
+    pub fn modes(&self) -> Vec<Sample> {
+        let Some(highest) = self
+            .data
+            .values()
+            .filter(|v| !v.is_zero())
+            .max_by(|a, b| a.partial_cmp(b).expect("Probabilities must be comparable."))
+        else {
+            return Vec::new();
+        };
+        self.data
+            .iter()
+            .filter(|(_, v)| *v == highest)
+            .map(|(k, _)| *k)
+            .collect()
+    }
+
+    /// The lowest of the most likely outcomes.
+    pub fn mode(&self) -> Option<Sample> {
+        self.modes().first().copied()
+    }
+}
+
+impl<T: Number, const SOUND: bool> DiscStats for PDF<T, SOUND> {
+    fn mean(&self) -> f64 {
+        PDF::mean(self)
+    }
+
+    fn variance(&self) -> f64 {
+        PDF::variance(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_close;
+    use num::BigRational;
+
+    #[test]
+    fn moments() {
+        let d6 = PDF::<f64, true>::die(6);
+        assert_close(d6.mean(), 3.5);
+        assert_close(d6.variance(), 35.0 / 12.0);
+        assert_close(d6.std_dev(), (35.0f64 / 12.0).sqrt());
+        assert_close(d6.skewness(), 0.0);
+        // The excess kurtosis of a discrete uniform distribution is -6(n^2 + 1) / 5(n^2 - 1)
+        assert_close(d6.kurtosis(), 3.0 - 6.0 * 37.0 / (5.0 * 35.0));
+        assert_close(d6.raw_moment(2), 91.0 / 6.0);
+        assert_close(DiscStats::mean(&d6), 3.5);
+        assert_close(DiscStats::variance(&d6), 35.0 / 12.0);
+
+        // Far away from 0, the raw moments would cancel catastrophically
+        let shifted = PDF::ntimes(2, &d6).offset(1_000_000_000);
+        assert_close(shifted.variance(), 35.0 / 6.0);
+
+        let mut adv = PDF::<f64, true>::die(20);
+        adv.with_advantage(1);
+        assert!(adv.skewness() < 0.0);
+
+        // Unsound PDFs are treated as if they were normalized
+        assert_close(d6.clone().scale_probabilities(0.5).mean(), 3.5);
+        assert!(PDF::<f64, false>::new().mean().is_nan());
+    }
+
+    #[test]
+    fn order_statistics() {
+        let d6 = PDF::<BigRational, true>::die(6);
+        assert_eq!(d6.median(), Some(3));
+        assert_eq!(d6.quantile(0.0), Some(1));
+        assert_eq!(d6.quantile(0.51), Some(4));
+        assert_eq!(d6.quantile(1.0), Some(6));
+        assert_eq!(d6.quantile(1.5), None);
+        assert_eq!(d6.modes(), vec![1, 2, 3, 4, 5, 6]);
+        assert_close(d6.mean(), 3.5);
+
+        let two_d6 = PDF::ntimes(2, &PDF::<f64, true>::die(6));
+        assert_eq!(two_d6.median(), Some(7));
+        assert_eq!(two_d6.mode(), Some(7));
+        assert_eq!(
+            (two_d6.min_outcome(), two_d6.max_outcome()),
+            (Some(2), Some(12))
+        );
+
+        let empty = PDF::<f64, false>::new();
+        assert_eq!(
+            (empty.min_outcome(), empty.median(), empty.mode()),
+            (None, None, None)
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Information about probability types needed to validate PDFs.
+
+use std::ops::Add;
+
+use num::{BigInt, BigRational, BigUint};
+
+use crate::numerics::{Fpp, LogProb};
+
+/// Describes how precisely a probability type represents values.
+pub trait Precision: Sized {
+    /// Whether arithmetic on this type is exact.
+    /// If so, the probabilities of a sound PDF must sum to exactly 1, instead of approximately.
+    const EXACT: bool = false;
+
+    /// Add two values, returning None when the sum does not fit in the type.
+    /// Bounded types like `Fpp` and the primitive integers override this,
+    /// so that a total above their maximum is reported instead of panicking.
+    fn try_add(self, rhs: &Self) -> Option<Self>
+    where
+        for<'a> Self: Add<&'a Self, Output = Self>,
+    {
+        Some(self + rhs)
+    }
+}
+
+impl Precision for f32 {}
+impl Precision for f64 {}
+impl Precision for Fpp {
+    fn try_add(self, rhs: &Self) -> Option<Self> {
+        self.checked_add(*rhs)
+    }
+}
+impl Precision for LogProb {}
+
+impl Precision for BigRational {
+    const EXACT: bool = true;
+}
+
+impl Precision for BigInt {
+    const EXACT: bool = true;
+}
+
+impl Precision for BigUint {
+    const EXACT: bool = true;
+}
+
+macro_rules! impl_precision_int {
+    ($($t:ty),*) => {
+        $(
+            impl Precision for $t {
+                const EXACT: bool = true;
+
+                fn try_add(self, rhs: &Self) -> Option<Self> {
+                    self.checked_add(*rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_precision_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
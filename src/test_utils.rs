@@ -0,0 +1,27 @@
+//! Helpers shared by the unit tests.
+
+use std::collections::BTreeMap;
+
+use num::BigRational;
+
+use crate::Sample;
+
+/// The largest absolute difference between two probabilities that are considered equal.
+const TOLERANCE: f64 = 1e-12;
+
+/// The exact rational n / d.
+pub fn ratio(n: i64, d: i64) -> BigRational {
+    BigRational::new(n.into(), d.into())
+}
+
+pub fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < TOLERANCE, "{a} != {b}");
+}
+
+/// Assert that both PDFs have the same outcomes, with probabilities that are equal up to rounding.
+pub fn assert_data_close(a: &BTreeMap<Sample, f64>, b: &BTreeMap<Sample, f64>) {
+    assert_eq!(a.keys().collect::<Vec<_>>(), b.keys().collect::<Vec<_>>());
+    for (x, y) in a.values().zip(b.values()) {
+        assert_close(*x, *y);
+    }
+}
@@ -1,3 +1,6 @@
+// Apart from DiscStats, which PDF implements, this API is a sketch that nothing implements yet.
+#![allow(dead_code)]
+
 use crate::LlDoiceError;
 
 type Sample = f64;
@@ -42,18 +45,20 @@ where
     unsafe fn assert_soundness(self) -> impl ProbabilityDistribution<Prob, true>;
 }
 
+/// Basic properties of a discrete distribution, which are implemented by `PDF`.
+pub trait DiscStats {
+    fn mean(&self) -> Sample;
+    fn variance(&self) -> Sample;
+}
+
 /// For discrete operations, it should be possible to provide a lot of default implementations.
-pub trait DiscPdf<Prob, const SOUND: bool> {
+pub trait DiscPdf<Prob, const SOUND: bool>: DiscStats {
     type Base: ProbabilityDistribution<Prob, SOUND>;
 
     // Basic usage
     fn p(&self, x: DSample) -> Prob;
     fn sample(&self) -> DSample;
 
-    // Basic properties
-    fn mean(&self) -> Sample;
-    fn variance(&self) -> Sample;
-
     // Operations
     fn advantage(&mut self);
     fn autoconvolute(&mut self, n: usize);